@@ -0,0 +1,176 @@
+use crate::RedisInstance;
+
+/// A node address as announced in `CLUSTER NODES`.
+///
+/// Redis 7 writes `ip:port@cport[,hostname[,aux=value...]]`, where the IP may
+/// be IPv6 without brackets and the hostname comes from
+/// `cluster-announce-hostname`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NodeAddr {
+    /// Empty for nodes whose address is not known yet (`noaddr`).
+    pub(crate) ip: String,
+    pub(crate) port: u16,
+    pub(crate) cport: Option<u16>,
+    pub(crate) hostname: Option<String>,
+    pub(crate) tls_port: Option<u16>,
+    /// Plaintext port announced when `port` is the TLS port (`tls-cluster yes`).
+    pub(crate) tcp_port: Option<u16>,
+}
+
+impl NodeAddr {
+    /// The instance to connect to, or `None` when the node has no usable address.
+    pub(crate) fn instance(&self) -> Option<RedisInstance> {
+        // Connections are plaintext, so prefer the TCP port over a TLS one.
+        let port: u16 = self.tcp_port.unwrap_or(self.port);
+        let host: &str = match (self.ip.as_str(), &self.hostname) {
+            ("", Some(hostname)) => hostname,
+            (ip, _) => ip,
+        };
+        if host.is_empty() || port == 0 {
+            return None;
+        }
+        Some(RedisInstance { ip: host.to_string(), port: port.to_string(), ..Default::default() })
+    }
+}
+
+/// Strips the brackets of an IPv6 literal, e.g. `[::1]` -> `::1`.
+pub(crate) fn normalize_host(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+/// Host as it must appear in a URI, bracketed when it is an IPv6 literal.
+pub(crate) fn uri_host(host: &str) -> String {
+    let host: &str = normalize_host(host);
+    if host.contains(':') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// Splits `host:port`, `[v6]:port` or a bare `v6:port` on the last colon.
+pub(crate) fn split_host_port(addr: &str) -> Option<(String, u16)> {
+    let (host, port) = addr.rsplit_once(':')?;
+    Some((normalize_host(host).to_string(), port.parse().ok()?))
+}
+
+/// Builds an instance from a host and port reported in INFO.
+pub(crate) fn instance(host: &str, port: &str) -> Option<RedisInstance> {
+    let host: &str = normalize_host(host.trim());
+    let port: u16 = port.trim().parse().ok()?;
+    if host.is_empty() || port == 0 {
+        return None;
+    }
+    Some(RedisInstance { ip: host.to_string(), port: port.to_string(), ..Default::default() })
+}
+
+/// Parses the address field of a `CLUSTER NODES` line.
+pub(crate) fn parse_cluster_addr(field: &str) -> Option<NodeAddr> {
+    let mut parts = field.split(',');
+    let endpoint: &str = parts.next()?;
+    let (host_port, cport) = match endpoint.split_once('@') {
+        Some((host_port, cport)) => (host_port, Some(cport.parse().ok()?)),
+        None => (endpoint, None),
+    };
+    let (ip, port) = split_host_port(host_port)?;
+    let mut addr = NodeAddr { ip, port, cport, hostname: None, tls_port: None, tcp_port: None };
+    for (i, part) in parts.enumerate() {
+        match part.split_once('=') {
+            Some(("tls-port", v)) => addr.tls_port = v.parse().ok().filter(|p| *p != 0),
+            Some(("tcp-port", v)) => addr.tcp_port = v.parse().ok().filter(|p| *p != 0),
+            Some(("hostname", v)) if !v.is_empty() => addr.hostname = Some(v.to_string()),
+            Some(_) => {}
+            // The hostname is the only positional field after the endpoint.
+            None if i == 0 && !part.is_empty() => addr.hostname = Some(part.to_string()),
+            None => {}
+        }
+    }
+    Some(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(ip: &str, port: u16, cport: Option<u16>) -> NodeAddr {
+        NodeAddr { ip: ip.to_string(), port, cport, hostname: None, tls_port: None, tcp_port: None }
+    }
+
+    #[test]
+    fn plain_addresses() {
+        assert_eq!(parse_cluster_addr("10.0.0.1:6379@16379"), Some(addr("10.0.0.1", 6379, Some(16379))));
+        // Before Redis 4 there was no cluster bus port
+        assert_eq!(parse_cluster_addr("10.0.0.1:6379"), Some(addr("10.0.0.1", 6379, None)));
+        assert_eq!(parse_cluster_addr("10.0.0.1"), None);
+        assert_eq!(parse_cluster_addr("10.0.0.1:6379@bus"), None);
+    }
+
+    #[test]
+    fn ipv6_is_written_without_brackets() {
+        assert_eq!(parse_cluster_addr("2001:db8::1:6379@16379"), Some(addr("2001:db8::1", 6379, Some(16379))));
+        assert_eq!(parse_cluster_addr("::1:7000@17000"), Some(addr("::1", 7000, Some(17000))));
+        assert_eq!(split_host_port("2001:db8::1:6379"), Some(("2001:db8::1".to_string(), 6379)));
+        assert_eq!(split_host_port("[2001:db8::1]:6379"), Some(("2001:db8::1".to_string(), 6379)));
+        assert_eq!(split_host_port("redis-1:6379"), Some(("redis-1".to_string(), 6379)));
+        assert_eq!(split_host_port("redis-1"), None);
+        assert_eq!(split_host_port("redis-1:port"), None);
+    }
+
+    #[test]
+    fn hostname_follows_the_endpoint() {
+        let parsed: NodeAddr = parse_cluster_addr("10.0.0.1:6379@16379,redis-1.example").unwrap();
+        assert_eq!(parsed.hostname.as_deref(), Some("redis-1.example"));
+        // Redis 7 leaves the field empty when no hostname is announced
+        assert_eq!(parse_cluster_addr("10.0.0.1:6379@16379,").unwrap().hostname, None);
+        let parsed: NodeAddr = parse_cluster_addr("10.0.0.1:6379@16379,,shard-id=abc").unwrap();
+        assert_eq!(parsed, addr("10.0.0.1", 6379, Some(16379)));
+        let parsed: NodeAddr = parse_cluster_addr("10.0.0.1:6379@16379,,hostname=redis-1").unwrap();
+        assert_eq!(parsed.hostname.as_deref(), Some("redis-1"));
+    }
+
+    #[test]
+    fn tls_and_tcp_ports() {
+        let parsed: NodeAddr = parse_cluster_addr("10.0.0.1:6379@16379,redis-1,tls-port=6380,shard-id=abc").unwrap();
+        assert_eq!(parsed.tls_port, Some(6380));
+        assert_eq!(parsed.tcp_port, None);
+        assert_eq!(parsed.instance().unwrap().port, "6379");
+
+        // With `tls-cluster yes` the announced port is the TLS one
+        let parsed: NodeAddr = parse_cluster_addr("10.0.0.1:6380@16379,,tcp-port=6379").unwrap();
+        assert_eq!(parsed.tcp_port, Some(6379));
+        let instance: RedisInstance = parsed.instance().unwrap();
+        assert_eq!((instance.ip.as_str(), instance.port.as_str()), ("10.0.0.1", "6379"));
+
+        let parsed: NodeAddr = parse_cluster_addr("10.0.0.1:6379@16379,,tls-port=0,tcp-port=0").unwrap();
+        assert_eq!((parsed.tls_port, parsed.tcp_port), (None, None));
+    }
+
+    #[test]
+    fn noaddr_nodes_have_no_instance() {
+        let parsed: NodeAddr = parse_cluster_addr(":0@0").unwrap();
+        assert_eq!(parsed, addr("", 0, Some(0)));
+        assert!(parsed.instance().is_none());
+        // A node known only by its hostname can still be polled
+        let instance: RedisInstance = parse_cluster_addr(":6379@16379,redis-1").unwrap().instance().unwrap();
+        assert_eq!((instance.ip.as_str(), instance.port.as_str()), ("redis-1", "6379"));
+    }
+
+    #[test]
+    fn uri_hosts_bracket_ipv6() {
+        assert_eq!(uri_host("10.0.0.1"), "10.0.0.1");
+        assert_eq!(uri_host("redis-1"), "redis-1");
+        assert_eq!(uri_host("::1"), "[::1]");
+        assert_eq!(uri_host("[2001:db8::1]"), "[2001:db8::1]");
+    }
+
+    #[test]
+    fn info_addresses() {
+        let parsed: RedisInstance = instance(" [::1] ", "6379\r").unwrap();
+        assert_eq!((parsed.ip.as_str(), parsed.port.as_str()), ("::1", "6379"));
+        assert!(instance("", "6379").is_none());
+        assert!(instance("10.0.0.1", "0").is_none());
+        assert!(instance("10.0.0.1", "?").is_none());
+    }
+}
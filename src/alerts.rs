@@ -0,0 +1,392 @@
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    collector::{InfoMap, NodeReport, NodeStatus},
+    rules::{self, Condition, Target},
+    RedisConfig, Webhook,
+};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+// How long an alert is kept while its rule cannot be evaluated, e.g. on a
+// node that went down for good and left no INFO.
+const UNEVALUATED_TTL_MS: u64 = 60 * 1000;
+
+struct Rule {
+    name: String,
+    severity: String,
+    when: String,
+    condition: Condition,
+    clusters: Option<Vec<String>>,
+}
+
+/// The alert rules and webhooks of the config, shared by every collector.
+pub(crate) struct Alerting {
+    rules: Vec<Rule>,
+    webhooks: Vec<Webhook>,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AlertState {
+    Pending,
+    Firing,
+    Resolved,
+}
+
+/// One rule matching on one node.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Alert {
+    pub(crate) rule: String,
+    pub(crate) severity: String,
+    pub(crate) cluster: String,
+    /// `ip:port` of the node.
+    pub(crate) node: String,
+    pub(crate) state: AlertState,
+    /// The rule's condition as written in the config.
+    pub(crate) when: String,
+    /// Left-hand side of the condition on the last evaluation.
+    pub(crate) value: String,
+    /// Unix milliseconds of the first evaluation the condition held.
+    pub(crate) since: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) fired_at: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) resolved_at: Option<u64>,
+    /// Unix milliseconds of the last evaluation of the condition.
+    #[serde(skip)]
+    pub(crate) evaluated_at: u64,
+}
+
+impl Alerting {
+    /// Parses every rule condition, failing on the first invalid one.
+    pub(crate) fn from_config(config: &RedisConfig) -> Result<Self, String> {
+        let rules: Vec<Rule> = config.alerts.iter()
+            .map(|rule| {
+                let condition: Condition = rules::parse(&rule.when)
+                    .map_err(|e| format!("alert '{}': {}", rule.name, e))?;
+                Ok(Rule {
+                    name: rule.name.clone(),
+                    severity: rule.severity.clone(),
+                    when: rule.when.clone(),
+                    condition,
+                    clusters: rule.clusters.clone(),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        let client: reqwest::Client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| format!("failed to build the webhook client: {}", e))?;
+        Ok(Alerting { rules, webhooks: config.webhooks.clone(), client })
+    }
+
+    /// POSTs alert transitions of one cluster to every webhook, in the background.
+    pub(crate) fn notify(&self, cluster: &str, alerts: Vec<Alert>) {
+        if alerts.is_empty() {
+            return;
+        }
+        let payload = json!({ "cluster": cluster, "alerts": alerts });
+        for webhook in &self.webhooks {
+            let mut request = self.client.post(&webhook.url).json(&payload);
+            for (name, value) in &webhook.headers {
+                request = request.header(name, value);
+            }
+            let url: String = webhook.url.clone();
+            tokio::spawn(async move {
+                match request.send().await.and_then(|res| res.error_for_status()) {
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Failed to deliver alerts to {}: {}", url, e),
+                }
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct TrackerState {
+    // Pending and firing alerts by (rule, node)
+    active: HashMap<(String, String), Alert>,
+    // Last INFO of every reachable node, for `rate()`
+    prev: HashMap<String, (InfoMap, u64)>,
+}
+
+/// Pending/firing/resolved state of the alert rules for one cluster.
+pub(crate) struct AlertTracker {
+    alerting: Arc<Alerting>,
+    cluster: String,
+    state: Mutex<TrackerState>,
+}
+
+impl AlertTracker {
+    pub(crate) fn new(alerting: Arc<Alerting>, cluster: &str) -> Self {
+        AlertTracker { alerting, cluster: cluster.to_string(), state: Mutex::new(TrackerState::default()) }
+    }
+
+    /// Evaluates every rule against a snapshot and returns the alerts that
+    /// started firing or resolved on this tick.
+    ///
+    /// A condition must hold on every tick for the rule's `for` duration
+    /// before the alert fires; an alert is only reported once per transition.
+    /// It resolves once the condition evaluates false or the node leaves the
+    /// topology. A tick where the condition cannot be evaluated keeps it as
+    /// it was, for up to a minute since the last evaluation.
+    pub(crate) fn evaluate(&self, reports: &[NodeReport], ts: u64) -> Vec<Alert> {
+        let mut guard = self.state.lock().unwrap();
+        let state: &mut TrackerState = &mut guard;
+        let mut transitions: Vec<Alert> = Vec::new();
+        let mut holding: HashSet<(String, String)> = HashSet::new();
+
+        let rules = self.alerting.rules.iter()
+            .filter(|r| r.clusters.as_ref().is_none_or(|c| c.contains(&self.cluster)));
+        for rule in rules {
+            for report in reports {
+                let node: String = report.addr();
+                let target = Target {
+                    report,
+                    prev: state.prev.get(&node).map(|(info, at)| (info, *at)),
+                    ts,
+                };
+                let key: (String, String) = (rule.name.clone(), node.clone());
+                let value: String = match rule.condition.check(&target) {
+                    Some((true, value)) => value,
+                    Some((false, _)) => continue,
+                    // The rule does not apply on this tick, e.g. one poll timed out and
+                    // left no INFO; the alert stays as it was rather than flapping.
+                    None => {
+                        if state.active.get(&key).is_some_and(|a| ts.saturating_sub(a.evaluated_at) < UNEVALUATED_TTL_MS) {
+                            holding.insert(key);
+                        }
+                        continue;
+                    }
+                };
+                holding.insert(key.clone());
+                let alert: &mut Alert = state.active.entry(key).or_insert_with(|| Alert {
+                    rule: rule.name.clone(),
+                    severity: rule.severity.clone(),
+                    cluster: self.cluster.clone(),
+                    node,
+                    state: AlertState::Pending,
+                    when: rule.when.clone(),
+                    value: String::new(),
+                    since: ts,
+                    fired_at: None,
+                    resolved_at: None,
+                    evaluated_at: ts,
+                });
+                alert.value = value;
+                alert.evaluated_at = ts;
+                if alert.state == AlertState::Pending && ts.saturating_sub(alert.since) >= rule.condition.hold_ms {
+                    alert.state = AlertState::Firing;
+                    alert.fired_at = Some(ts);
+                    transitions.push(alert.clone());
+                }
+            }
+        }
+
+        // Everything that stopped holding, and nodes that left the topology.
+        let stopped: Vec<(String, String)> = state.active.keys()
+            .filter(|key| !holding.contains(*key))
+            .cloned()
+            .collect();
+        for key in stopped {
+            let Some(mut alert) = state.active.remove(&key) else {
+                continue;
+            };
+            if alert.state == AlertState::Firing {
+                alert.state = AlertState::Resolved;
+                alert.resolved_at = Some(ts);
+                transitions.push(alert);
+            }
+        }
+
+        state.prev.clear();
+        for report in reports.iter().filter(|r| r.status == NodeStatus::Up) {
+            state.prev.insert(report.addr(), (report.info.clone(), ts));
+        }
+        transitions
+    }
+
+    /// Pending and firing alerts, firing first.
+    pub(crate) fn active(&self) -> Vec<Alert> {
+        let mut alerts: Vec<Alert> = self.state.lock().unwrap().active.values().cloned().collect();
+        alerts.sort_by(|a, b| {
+            (b.state == AlertState::Firing).cmp(&(a.state == AlertState::Firing))
+                .then_with(|| a.rule.cmp(&b.rule))
+                .then_with(|| a.node.cmp(&b.node))
+        });
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::NodeProbe, error::CollectError, RedisInstance};
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use tokio::{net::TcpListener, sync::mpsc};
+
+    fn alerting(config: serde_json::Value) -> Arc<Alerting> {
+        let config: RedisConfig = serde_json::from_value(config).unwrap();
+        Arc::new(Alerting::from_config(&config).unwrap())
+    }
+
+    fn instance() -> RedisInstance {
+        RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() }
+    }
+
+    fn node(used_memory: u64) -> NodeReport {
+        let info: InfoMap = [("used_memory".to_string(), used_memory.to_string())].into();
+        NodeReport::new(&instance(), "master", None, Ok(NodeProbe { info, ..Default::default() }))
+    }
+
+    fn timed_out() -> NodeReport {
+        NodeReport::new(&instance(), "master", None, Err(CollectError::Timeout("no reply within 1000 ms".to_string())))
+    }
+
+    fn active(tracker: &AlertTracker) -> Vec<Alert> {
+        tracker.state.lock().unwrap().active.values().cloned().collect()
+    }
+
+    fn states(alerts: &[Alert]) -> Vec<AlertState> {
+        alerts.iter().map(|a| a.state).collect()
+    }
+
+    #[test]
+    fn alerts_go_from_pending_to_firing_to_resolved() {
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "memory", "when": "used_memory > 100 for 2s" }],
+        }));
+        let tracker = AlertTracker::new(alerting, "s1");
+
+        assert!(tracker.evaluate(&[node(200)], 0).is_empty());
+        assert_eq!(states(&active(&tracker)), [AlertState::Pending]);
+        assert!(tracker.evaluate(&[node(200)], 1000).is_empty());
+
+        let fired: Vec<Alert> = tracker.evaluate(&[node(200)], 2000);
+        assert_eq!(states(&fired), [AlertState::Firing]);
+        assert_eq!(fired[0].node, "10.0.0.1:6379");
+        assert_eq!(fired[0].value, "200");
+        assert_eq!(fired[0].since, 0);
+        assert_eq!(fired[0].fired_at, Some(2000));
+        // Reported once, not on every tick it keeps firing
+        assert!(tracker.evaluate(&[node(300)], 3000).is_empty());
+
+        let resolved: Vec<Alert> = tracker.evaluate(&[node(50)], 4000);
+        assert_eq!(states(&resolved), [AlertState::Resolved]);
+        assert_eq!(resolved[0].resolved_at, Some(4000));
+        assert!(active(&tracker).is_empty());
+    }
+
+    #[test]
+    fn a_pending_alert_that_stops_holding_is_dropped_silently() {
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "memory", "when": "used_memory > 100 for 2s" }],
+        }));
+        let tracker = AlertTracker::new(alerting, "s1");
+        assert!(tracker.evaluate(&[node(200)], 0).is_empty());
+        assert!(tracker.evaluate(&[node(50)], 1000).is_empty());
+        assert!(active(&tracker).is_empty());
+        // The hold starts over
+        assert!(tracker.evaluate(&[node(200)], 2000).is_empty());
+        assert!(tracker.evaluate(&[node(200)], 3000).is_empty());
+        assert_eq!(states(&tracker.evaluate(&[node(200)], 4000)), [AlertState::Firing]);
+    }
+
+    #[test]
+    fn a_tick_without_info_neither_resolves_nor_refires() {
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "memory", "when": "used_memory > 100" }],
+        }));
+        let tracker = AlertTracker::new(alerting, "s1");
+        assert_eq!(states(&tracker.evaluate(&[node(200)], 0)), [AlertState::Firing]);
+        assert!(tracker.evaluate(&[timed_out()], 1000).is_empty());
+        assert_eq!(states(&active(&tracker)), [AlertState::Firing]);
+        assert!(tracker.evaluate(&[node(200)], 2000).is_empty());
+        assert_eq!(active(&tracker)[0].fired_at, Some(0));
+    }
+
+    #[test]
+    fn alerts_of_nodes_that_stay_down_resolve() {
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "memory", "when": "used_memory > 100" }],
+        }));
+        let tracker = AlertTracker::new(alerting, "s1");
+        assert_eq!(states(&tracker.evaluate(&[node(200)], 0)), [AlertState::Firing]);
+        assert!(tracker.evaluate(&[timed_out()], UNEVALUATED_TTL_MS - 1000).is_empty());
+        let resolved: Vec<Alert> = tracker.evaluate(&[timed_out()], UNEVALUATED_TTL_MS);
+        assert_eq!(states(&resolved), [AlertState::Resolved]);
+        assert_eq!(resolved[0].resolved_at, Some(UNEVALUATED_TTL_MS));
+        assert!(active(&tracker).is_empty());
+        assert!(tracker.evaluate(&[timed_out()], UNEVALUATED_TTL_MS + 1000).is_empty());
+    }
+
+    #[test]
+    fn alerts_of_nodes_that_left_the_topology_resolve() {
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "down", "when": "status != up" }],
+        }));
+        let tracker = AlertTracker::new(alerting, "s1");
+        assert_eq!(states(&tracker.evaluate(&[timed_out()], 0)), [AlertState::Firing]);
+        assert_eq!(states(&tracker.evaluate(&[], 1000)), [AlertState::Resolved]);
+    }
+
+    #[test]
+    fn rules_only_apply_to_their_clusters() {
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "memory", "when": "used_memory > 100", "clusters": ["other"] }],
+        }));
+        let tracker = AlertTracker::new(alerting, "s1");
+        assert!(tracker.evaluate(&[node(200)], 0).is_empty());
+        assert!(active(&tracker).is_empty());
+    }
+
+    type Received = (HeaderMap, serde_json::Value);
+
+    async fn hook(State(tx): State<mpsc::UnboundedSender<Received>>, headers: HeaderMap, Json(body): Json<serde_json::Value>) {
+        let _ = tx.send((headers, body));
+    }
+
+    #[tokio::test]
+    async fn transitions_are_posted_to_every_webhook() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Received>();
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/hook", post(hook)).with_state(tx);
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let alerting = alerting(json!({
+            "clusters": [],
+            "alerts": [{ "name": "memory", "when": "used_memory > 100", "severity": "critical" }],
+            "webhooks": [{ "url": format!("http://{}/hook", addr), "headers": { "Authorization": "Bearer secret" } }],
+        }));
+        let tracker = AlertTracker::new(alerting.clone(), "s1");
+        alerting.notify("s1", tracker.evaluate(&[node(200)], 1000));
+
+        let (headers, body) = tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(headers.get("authorization").unwrap(), "Bearer secret");
+        assert_eq!(headers.get("content-type").unwrap(), "application/json");
+        assert_eq!(body["cluster"], "s1");
+        let alerts = body["alerts"].as_array().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0]["rule"], "memory");
+        assert_eq!(alerts[0]["severity"], "critical");
+        assert_eq!(alerts[0]["node"], "10.0.0.1:6379");
+        assert_eq!(alerts[0]["state"], "firing");
+        assert_eq!(alerts[0]["value"], "200");
+        assert_eq!(alerts[0]["fired_at"], 1000);
+        assert!(alerts[0].get("resolved_at").is_none());
+    }
+}
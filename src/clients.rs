@@ -0,0 +1,434 @@
+use redis::aio::MultiplexedConnection;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{
+    addr,
+    collector::{query_within, task_failed, NodeStatus, Snapshot},
+    error::CollectError,
+    history::unix_millis,
+    pool::ConnectionPool,
+    RedisInstance,
+};
+
+// Groups listed per dimension in a summary; the rest is counted as one.
+const TOP_GROUPS: usize = 20;
+// Clients listed with the largest query and output buffers.
+const TOP_BUFFERS: usize = 10;
+// Clients listed as blocked or subscribed in a summary.
+const LISTED_CLIENTS: usize = 50;
+// Clients returned by one drill-down.
+const DRILL_DOWN_LIMIT: usize = 1000;
+// Upper bounds of the idle-time buckets, in seconds.
+const IDLE_BUCKETS: [(u64, &str); 5] = [(1, "<1s"), (10, "<10s"), (60, "<1m"), (600, "<10m"), (3600, "<1h")];
+
+/// One line of `CLIENT LIST`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct ClientInfo {
+    pub(crate) id: u64,
+    pub(crate) addr: String,
+    pub(crate) name: String,
+    /// `lib-name` and `lib-ver`, Redis 7.2 and later.
+    pub(crate) lib_name: String,
+    pub(crate) lib_ver: String,
+    pub(crate) age: u64,
+    pub(crate) idle: u64,
+    pub(crate) flags: String,
+    pub(crate) db: u64,
+    pub(crate) sub: u64,
+    pub(crate) psub: u64,
+    pub(crate) ssub: u64,
+    pub(crate) qbuf: u64,
+    pub(crate) omem: u64,
+    pub(crate) tot_mem: u64,
+    pub(crate) cmd: String,
+}
+
+impl ClientInfo {
+    fn host(&self) -> String {
+        addr::split_host_port(&self.addr).map(|(host, _)| host).unwrap_or_else(|| self.addr.clone())
+    }
+
+    fn is_blocked(&self) -> bool {
+        self.flags.contains('b')
+    }
+
+    fn is_pubsub(&self) -> bool {
+        self.sub + self.psub + self.ssub > 0 || self.flags.contains('P')
+    }
+
+    fn idle_bucket(&self) -> &'static str {
+        IDLE_BUCKETS.iter()
+            .find(|(limit, _)| self.idle < *limit)
+            .map(|(_, label)| *label)
+            .unwrap_or(">=1h")
+    }
+}
+
+/// Clients sharing a host, name or library.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ClientGroup {
+    pub(crate) key: String,
+    pub(crate) count: usize,
+    pub(crate) qbuf: u64,
+    pub(crate) omem: u64,
+    pub(crate) tot_mem: u64,
+    pub(crate) max_idle: u64,
+}
+
+/// Aggregates of one `CLIENT LIST` sample of a node.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ClientSummary {
+    /// Unix milliseconds the sample was taken at.
+    pub(crate) ts: u64,
+    pub(crate) total: usize,
+    /// Largest groups first; an empty key means the field is not set.
+    pub(crate) by_host: Vec<ClientGroup>,
+    pub(crate) by_name: Vec<ClientGroup>,
+    pub(crate) by_lib: Vec<ClientGroup>,
+    /// Client count per idle-time bucket, shortest first.
+    pub(crate) idle: Vec<(&'static str, usize)>,
+    pub(crate) largest_buffers: Vec<ClientInfo>,
+    pub(crate) blocked_count: usize,
+    pub(crate) blocked: Vec<ClientInfo>,
+    pub(crate) pubsub_count: usize,
+    pub(crate) pubsub: Vec<ClientInfo>,
+}
+
+/// Parses the reply of `CLIENT LIST`, one `key=value` list per line.
+pub(crate) fn parse_client_list(reply: &str) -> Vec<ClientInfo> {
+    reply.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut client = ClientInfo::default();
+            for (key, value) in line.split(' ').filter_map(|item| item.split_once('=')) {
+                let num = || value.parse().unwrap_or_default();
+                match key {
+                    "id" => client.id = num(),
+                    "addr" => client.addr = value.to_string(),
+                    "name" => client.name = value.to_string(),
+                    "lib-name" => client.lib_name = value.to_string(),
+                    "lib-ver" => client.lib_ver = value.to_string(),
+                    "age" => client.age = num(),
+                    "idle" => client.idle = num(),
+                    "flags" => client.flags = value.to_string(),
+                    "db" => client.db = num(),
+                    "sub" => client.sub = num(),
+                    "psub" => client.psub = num(),
+                    "ssub" => client.ssub = num(),
+                    "qbuf" => client.qbuf = num(),
+                    "omem" => client.omem = num(),
+                    "tot-mem" => client.tot_mem = num(),
+                    "cmd" => client.cmd = value.to_string(),
+                    _ => {}
+                }
+            }
+            client
+        })
+        .collect()
+}
+
+fn group_by(clients: &[ClientInfo], key: impl Fn(&ClientInfo) -> String) -> Vec<ClientGroup> {
+    let mut groups: HashMap<String, ClientGroup> = HashMap::new();
+    for client in clients {
+        let k: String = key(client);
+        let group: &mut ClientGroup = groups.entry(k.clone()).or_insert_with(|| ClientGroup {
+            key: k,
+            count: 0,
+            qbuf: 0,
+            omem: 0,
+            tot_mem: 0,
+            max_idle: 0,
+        });
+        group.count += 1;
+        group.qbuf += client.qbuf;
+        group.omem += client.omem;
+        group.tot_mem += client.tot_mem;
+        group.max_idle = group.max_idle.max(client.idle);
+    }
+    let mut groups: Vec<ClientGroup> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    if groups.len() > TOP_GROUPS {
+        let rest: Vec<ClientGroup> = groups.split_off(TOP_GROUPS - 1);
+        groups.push(ClientGroup {
+            key: format!("({} others)", rest.len()),
+            count: rest.iter().map(|g| g.count).sum(),
+            qbuf: rest.iter().map(|g| g.qbuf).sum(),
+            omem: rest.iter().map(|g| g.omem).sum(),
+            tot_mem: rest.iter().map(|g| g.tot_mem).sum(),
+            max_idle: rest.iter().map(|g| g.max_idle).max().unwrap_or_default(),
+        });
+    }
+    groups
+}
+
+/// Aggregates a `CLIENT LIST` sample.
+pub(crate) fn summarize(clients: &[ClientInfo], ts: u64) -> ClientSummary {
+    let mut idle: Vec<(&'static str, usize)> = IDLE_BUCKETS.iter().map(|(_, label)| (*label, 0)).collect();
+    idle.push((">=1h", 0));
+    for client in clients {
+        let bucket: &str = client.idle_bucket();
+        if let Some(entry) = idle.iter_mut().find(|(label, _)| *label == bucket) {
+            entry.1 += 1;
+        }
+    }
+    let mut largest: Vec<&ClientInfo> = clients.iter().filter(|c| c.qbuf + c.omem > 0).collect();
+    largest.sort_by_key(|c| std::cmp::Reverse(c.qbuf + c.omem));
+    let blocked: Vec<&ClientInfo> = clients.iter().filter(|c| c.is_blocked()).collect();
+    let pubsub: Vec<&ClientInfo> = clients.iter().filter(|c| c.is_pubsub()).collect();
+    ClientSummary {
+        ts,
+        total: clients.len(),
+        by_host: group_by(clients, ClientInfo::host),
+        by_name: group_by(clients, |c| c.name.clone()),
+        by_lib: group_by(clients, |c| c.lib_name.clone()),
+        idle,
+        largest_buffers: largest.into_iter().take(TOP_BUFFERS).cloned().collect(),
+        blocked_count: blocked.len(),
+        blocked: blocked.into_iter().take(LISTED_CLIENTS).cloned().collect(),
+        pubsub_count: pubsub.len(),
+        pubsub: pubsub.into_iter().take(LISTED_CLIENTS).cloned().collect(),
+    }
+}
+
+/// Which clients of a sample a drill-down returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClientFilter {
+    Host,
+    Name,
+    Lib,
+    Idle,
+    Blocked,
+    Pubsub,
+}
+
+impl ClientFilter {
+    pub(crate) fn parse(by: &str) -> Option<Self> {
+        match by {
+            "host" => Some(ClientFilter::Host),
+            "name" => Some(ClientFilter::Name),
+            "lib" => Some(ClientFilter::Lib),
+            "idle" => Some(ClientFilter::Idle),
+            "blocked" => Some(ClientFilter::Blocked),
+            "pubsub" => Some(ClientFilter::Pubsub),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, client: &ClientInfo, key: &str) -> bool {
+        match self {
+            ClientFilter::Host => client.host() == key,
+            ClientFilter::Name => client.name == key,
+            ClientFilter::Lib => client.lib_name == key,
+            ClientFilter::Idle => client.idle_bucket() == key,
+            ClientFilter::Blocked => client.is_blocked(),
+            ClientFilter::Pubsub => client.is_pubsub(),
+        }
+    }
+}
+
+struct Sample {
+    clients: Vec<ClientInfo>,
+    summary: ClientSummary,
+}
+
+/// Latest `CLIENT LIST` sample of every node of one cluster, keyed by `ip:port`.
+#[derive(Default)]
+pub(crate) struct ClientStore {
+    samples: Mutex<HashMap<String, Arc<Sample>>>,
+}
+
+impl ClientStore {
+    /// Summary of the latest sample of a node, if it was sampled.
+    pub(crate) fn summary(&self, node: &str) -> Option<ClientSummary> {
+        self.samples.lock().unwrap().get(node).map(|s| s.summary.clone())
+    }
+
+    /// Summaries of the latest sample of every node.
+    pub(crate) fn summaries(&self) -> HashMap<String, ClientSummary> {
+        self.samples.lock().unwrap().iter()
+            .map(|(node, sample)| (node.clone(), sample.summary.clone()))
+            .collect()
+    }
+
+    /// When the latest sample of a node was taken.
+    pub(crate) fn sampled_at(&self, node: &str) -> Option<u64> {
+        self.samples.lock().unwrap().get(node).map(|s| s.summary.ts)
+    }
+
+    /// Clients of the latest samples matching a drill-down, the most idle first.
+    ///
+    /// Every sampled node is searched unless `node` is given.
+    pub(crate) fn find(&self, node: Option<&str>, filter: ClientFilter, key: &str) -> Vec<(String, ClientInfo)> {
+        let samples: Vec<(String, Arc<Sample>)> = self.samples.lock().unwrap().iter()
+            .filter(|(addr, _)| node.is_none_or(|n| n == addr.as_str()))
+            .map(|(addr, sample)| (addr.clone(), sample.clone()))
+            .collect();
+        let mut found: Vec<(String, ClientInfo)> = samples.iter()
+            .flat_map(|(addr, sample)| {
+                sample.clients.iter()
+                    .filter(|c| filter.matches(c, key))
+                    .map(|c| (addr.clone(), c.clone()))
+            })
+            .collect();
+        found.sort_by(|a, b| b.1.idle.cmp(&a.1.idle).then_with(|| a.0.cmp(&b.0)));
+        found.truncate(DRILL_DOWN_LIMIT);
+        found
+    }
+}
+
+type ClientList = Result<Vec<ClientInfo>, CollectError>;
+
+async fn sample_node(pool: &ConnectionPool, instance: &RedisInstance, limit: Duration) -> ClientList {
+    let mut con: MultiplexedConnection = pool.get(instance).await?;
+    let reply: String = query_within(pool, instance, &mut con, redis::cmd("CLIENT").arg("LIST"), limit).await?;
+    Ok(parse_client_list(&reply))
+}
+
+/// Samples `CLIENT LIST` on every reachable node of the latest snapshot, once per interval.
+///
+/// Listing every client is expensive on busy nodes, so it runs apart from
+/// the one-second INFO poll, on connections of its own from `pool`, and
+/// may take up to one interval to answer. A node that fails to answer
+/// keeps its previous sample until it leaves the topology.
+pub(crate) fn spawn_sampler(
+    pool: Arc<ConnectionPool>,
+    latest: Arc<Mutex<Arc<Snapshot>>>,
+    samples: Arc<ClientStore>,
+    period: Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            pool.evict_idle();
+            let snapshot: Arc<Snapshot> = latest.lock().unwrap().clone();
+            let handles: Vec<(String, JoinHandle<ClientList>)> = snapshot.nodes.iter()
+                .filter(|r| r.status == NodeStatus::Up)
+                .map(|r| {
+                    let pool = pool.clone();
+                    let instance = RedisInstance { ip: r.ip.clone(), port: r.port.clone(), ..Default::default() };
+                    (r.addr(), tokio::spawn(async move { sample_node(&pool, &instance, period).await }))
+                })
+                .collect();
+            let mut sampled: Vec<(String, Sample)> = Vec::new();
+            for (node, handle) in handles {
+                match handle.await.unwrap_or_else(|e| Err(task_failed(e))) {
+                    Ok(clients) => {
+                        let summary: ClientSummary = summarize(&clients, unix_millis());
+                        sampled.push((node, Sample { clients, summary }));
+                    }
+                    Err(e) => eprintln!("Failed to sample the clients of {}: {}", node, e),
+                }
+            }
+            let mut guard = samples.samples.lock().unwrap();
+            guard.retain(|node, _| snapshot.nodes.iter().any(|r| r.addr() == *node));
+            guard.extend(sampled.into_iter().map(|(node, sample)| (node, Arc::new(sample))));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `CLIENT LIST` of a Redis 7.2 node.
+    const CLIENT_LIST: &str = "\
+id=3 addr=10.0.1.5:50412 laddr=10.0.0.1:6379 fd=8 name=worker age=3600 idle=0 flags=N db=0 sub=0 psub=0 ssub=0 multi=-1 qbuf=26 qbuf-free=20448 argv-mem=10 multi-mem=0 rbs=1024 rbp=0 obl=0 oll=0 omem=0 tot-mem=22426 events=r cmd=get user=default redir=-1 resp=2 lib-name=redis-py lib-ver=5.0.1
+id=4 addr=10.0.1.5:50414 laddr=10.0.0.1:6379 fd=9 name=worker age=3600 idle=30 flags=b db=0 sub=0 psub=0 ssub=0 multi=-1 qbuf=0 qbuf-free=0 argv-mem=0 multi-mem=0 rbs=1024 rbp=0 obl=0 oll=0 omem=0 tot-mem=1928 events=r cmd=blpop user=default redir=-1 resp=2 lib-name=redis-py lib-ver=5.0.1
+id=7 addr=10.0.1.6:40001 laddr=10.0.0.1:6379 fd=10 name= age=120 idle=120 flags=P db=0 sub=2 psub=1 ssub=0 multi=-1 qbuf=0 qbuf-free=0 argv-mem=0 multi-mem=0 rbs=1024 rbp=0 obl=0 oll=3 omem=16384 tot-mem=40000 events=rw cmd=subscribe user=default redir=-1 resp=3 lib-name=ioredis lib-ver=5.3.2
+id=9 addr=[::1]:53102 laddr=[::1]:6379 fd=11 name= age=7200 idle=7000 flags=N db=2 sub=0 psub=0 ssub=0 multi=-1 qbuf=0 qbuf-free=0 argv-mem=0 multi-mem=0 rbs=1024 rbp=0 obl=0 oll=0 omem=0 tot-mem=1928 events=r cmd=client|list user=default redir=-1 resp=2 lib-name= lib-ver=
+";
+
+    fn keys(groups: &[ClientGroup]) -> Vec<(&str, usize)> {
+        groups.iter().map(|g| (g.key.as_str(), g.count)).collect()
+    }
+
+    fn store(nodes: Vec<(&str, Vec<ClientInfo>)>) -> ClientStore {
+        let store = ClientStore::default();
+        for (node, clients) in nodes {
+            let summary: ClientSummary = summarize(&clients, 1000);
+            store.samples.lock().unwrap().insert(node.to_string(), Arc::new(Sample { clients, summary }));
+        }
+        store
+    }
+
+    #[test]
+    fn client_list_lines_are_parsed() {
+        let clients: Vec<ClientInfo> = parse_client_list(CLIENT_LIST);
+        assert_eq!(clients.len(), 4);
+        let worker: &ClientInfo = &clients[0];
+        assert_eq!((worker.id, worker.addr.as_str(), worker.name.as_str()), (3, "10.0.1.5:50412", "worker"));
+        assert_eq!((worker.lib_name.as_str(), worker.lib_ver.as_str()), ("redis-py", "5.0.1"));
+        assert_eq!((worker.age, worker.idle, worker.qbuf, worker.tot_mem), (3600, 0, 26, 22426));
+        assert_eq!(worker.cmd, "get");
+        let subscriber: &ClientInfo = &clients[2];
+        assert_eq!((subscriber.sub, subscriber.psub, subscriber.omem), (2, 1, 16384));
+        assert_eq!((clients[3].db, clients[3].name.as_str(), clients[3].host()), (2, "", "::1".to_string()));
+    }
+
+    #[test]
+    fn clients_are_grouped_by_host_name_lib_and_idle_time() {
+        let summary: ClientSummary = summarize(&parse_client_list(CLIENT_LIST), 1000);
+        assert_eq!(summary.total, 4);
+        assert_eq!(keys(&summary.by_host), [("10.0.1.5", 2), ("10.0.1.6", 1), ("::1", 1)]);
+        assert_eq!(keys(&summary.by_name), [("", 2), ("worker", 2)]);
+        assert_eq!(keys(&summary.by_lib), [("redis-py", 2), ("", 1), ("ioredis", 1)]);
+        let workers: &ClientGroup = summary.by_name.iter().find(|g| g.key == "worker").unwrap();
+        assert_eq!((workers.qbuf, workers.tot_mem, workers.max_idle), (26, 24354, 30));
+        assert_eq!(summary.idle, [("<1s", 1), ("<10s", 0), ("<1m", 1), ("<10m", 1), ("<1h", 0), (">=1h", 1)]);
+        let largest: Vec<u64> = summary.largest_buffers.iter().map(|c| c.id).collect();
+        assert_eq!(largest, [7, 3]);
+        assert_eq!((summary.blocked_count, summary.blocked[0].id), (1, 4));
+        assert_eq!((summary.pubsub_count, summary.pubsub[0].id), (1, 7));
+    }
+
+    #[test]
+    fn small_groups_are_counted_as_one() {
+        let clients: Vec<ClientInfo> = (0..30)
+            .map(|i| ClientInfo { addr: format!("10.0.2.{}:4000", i), idle: i, ..Default::default() })
+            .collect();
+        let summary: ClientSummary = summarize(&clients, 1000);
+        assert_eq!(summary.by_host.len(), TOP_GROUPS);
+        let others: &ClientGroup = summary.by_host.last().unwrap();
+        assert_eq!((others.key.as_str(), others.count, others.max_idle), ("(11 others)", 11, 29));
+    }
+
+    #[test]
+    fn drill_downs_match_the_summary_groups() {
+        let store: ClientStore = store(vec![
+            ("10.0.0.1:6379", parse_client_list(CLIENT_LIST)),
+            ("10.0.0.2:6379", parse_client_list(CLIENT_LIST)),
+        ]);
+        let ids = |found: Vec<(String, ClientInfo)>| -> Vec<(String, u64)> {
+            found.into_iter().map(|(node, c)| (node, c.id)).collect()
+        };
+        assert_eq!(store.find(Some("10.0.0.2:6379"), ClientFilter::Host, "10.0.1.5").len(), 2);
+        assert_eq!(store.find(None, ClientFilter::Name, "worker").len(), 4);
+        assert_eq!(store.find(None, ClientFilter::Lib, "ioredis").len(), 2);
+        assert_eq!(
+            ids(store.find(None, ClientFilter::Idle, ">=1h")),
+            [("10.0.0.1:6379".to_string(), 9), ("10.0.0.2:6379".to_string(), 9)],
+        );
+        assert_eq!(ids(store.find(Some("10.0.0.1:6379"), ClientFilter::Blocked, "")), [("10.0.0.1:6379".to_string(), 4)]);
+        assert_eq!(ids(store.find(Some("10.0.0.1:6379"), ClientFilter::Pubsub, "")), [("10.0.0.1:6379".to_string(), 7)]);
+        assert_eq!(ClientFilter::parse("pubsub"), Some(ClientFilter::Pubsub));
+        assert_eq!(ClientFilter::parse("addr"), None);
+    }
+
+    #[test]
+    fn a_drill_down_returns_the_most_idle_thousand() {
+        let clients: Vec<ClientInfo> = (0..1500)
+            .map(|i| ClientInfo { id: i, addr: "10.0.3.1:5000".to_string(), idle: i, ..Default::default() })
+            .collect();
+        let found: Vec<(String, ClientInfo)> = store(vec![("10.0.0.1:6379", clients)]).find(None, ClientFilter::Host, "10.0.3.1");
+        assert_eq!(found.len(), DRILL_DOWN_LIMIT);
+        assert_eq!((found[0].1.idle, found[DRILL_DOWN_LIMIT - 1].1.idle), (1499, 500));
+    }
+}
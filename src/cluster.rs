@@ -0,0 +1,543 @@
+use redis::aio::MultiplexedConnection;
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc};
+use tokio::task::JoinHandle;
+
+use crate::{
+    addr::{self, NodeAddr},
+    collector::{collect_master, parse_redis_info, probe_node, query, task_failed, InfoMap, NodeReport, ProbeTask, Snapshot},
+    consistency::{self, Consistency},
+    error::CollectError,
+    history::info_num,
+    pool::ConnectionPool,
+    RedisInstance,
+};
+
+/// A node as the cluster itself describes it in `CLUSTER NODES`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ClusterNodeView {
+    pub(crate) id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) hostname: Option<String>,
+    /// e.g. `myself,master`, `slave,fail?`, `master,fail,noaddr`, `handshake`
+    pub(crate) flags: Vec<String>,
+    /// Node ID of the master a replica follows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) master_id: Option<String>,
+    /// `connected` or `disconnected` as seen by the queried node.
+    pub(crate) link_state: String,
+}
+
+/// A slot being moved between two masters during resharding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct SlotMigration {
+    pub(crate) slot: u16,
+    /// Node IDs of the source and target masters.
+    pub(crate) from: String,
+    pub(crate) to: String,
+    /// Whether the source has the slot in `MIGRATING` state.
+    pub(crate) migrating: bool,
+    /// Whether the target has the slot in `IMPORTING` state.
+    pub(crate) importing: bool,
+}
+
+/// The slots one master serves.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SlotOwner {
+    pub(crate) id: String,
+    pub(crate) addr: String,
+    /// Inclusive `[start, end]` ranges.
+    pub(crate) ranges: Vec<(u16, u16)>,
+    pub(crate) count: u32,
+}
+
+/// Slot coverage of a Redis Cluster, as seen by the seed.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SlotMap {
+    pub(crate) owners: Vec<SlotOwner>,
+    pub(crate) uncovered: Vec<(u16, u16)>,
+    pub(crate) covered: u32,
+    pub(crate) migrations: Vec<SlotMigration>,
+}
+
+/// Cluster-wide health from `CLUSTER INFO` on the seed.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ClusterHealth {
+    /// `ip:port` of the node that answered.
+    pub(crate) seed: String,
+    /// `ok` or `fail`
+    pub(crate) state: String,
+    pub(crate) slots_assigned: u32,
+    pub(crate) slots_ok: u32,
+    pub(crate) slots_pfail: u32,
+    pub(crate) slots_fail: u32,
+    pub(crate) known_nodes: u32,
+    pub(crate) size: u32,
+    pub(crate) current_epoch: u64,
+}
+
+/// One parsed line of `CLUSTER NODES`.
+#[derive(Debug, Clone)]
+pub(crate) struct ClusterNode {
+    pub(crate) view: ClusterNodeView,
+    pub(crate) addr: NodeAddr,
+    pub(crate) config_epoch: u64,
+    pub(crate) slots: Vec<(u16, u16)>,
+    /// `[slot->-id]` and `[slot-<-id]` markers, only present on the `myself` line.
+    pub(crate) migrations: Vec<SlotMigration>,
+}
+
+impl ClusterNode {
+    pub(crate) fn has_flag(&self, flag: &str) -> bool {
+        self.view.flags.iter().any(|f| f == flag)
+    }
+
+    pub(crate) fn is_master(&self) -> bool {
+        self.has_flag("master")
+    }
+
+    pub(crate) fn is_replica(&self) -> bool {
+        self.has_flag("slave") || self.has_flag("replica")
+    }
+
+    /// Address to poll, or why the node cannot be polled.
+    fn instance(&self) -> Result<RedisInstance, CollectError> {
+        if self.has_flag("handshake") {
+            return Err(CollectError::ClusterState("node is still in handshake".to_string()));
+        }
+        match self.addr.instance() {
+            Some(instance) if !self.has_flag("noaddr") => Ok(instance),
+            _ => Err(CollectError::ClusterState("cluster has no address for this node".to_string())),
+        }
+    }
+
+    // Where an unpollable node is reported; the address may be partial.
+    pub(crate) fn placeholder(&self) -> RedisInstance {
+        RedisInstance {
+            ip: self.addr.ip.clone(),
+            port: self.addr.port.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) const SLOT_COUNT: usize = 16384;
+
+// Parses a slot field: `0-5460`, `5461`, `[93->-id]` or `[77-<-id]`.
+fn parse_slot_field(node_id: &str, field: &str, node: &mut ClusterNode) {
+    if let Some(marker) = field.strip_prefix('[').and_then(|f| f.strip_suffix(']')) {
+        let migration = if let Some((slot, peer)) = marker.split_once("->-") {
+            slot.parse().ok().map(|slot| SlotMigration {
+                slot, from: node_id.to_string(), to: peer.to_string(), migrating: true, importing: false,
+            })
+        } else if let Some((slot, peer)) = marker.split_once("-<-") {
+            slot.parse().ok().map(|slot| SlotMigration {
+                slot, from: peer.to_string(), to: node_id.to_string(), migrating: false, importing: true,
+            })
+        } else {
+            None
+        };
+        node.migrations.extend(migration);
+        return;
+    }
+    let range = match field.split_once('-') {
+        Some((start, end)) => start.parse().ok().zip(end.parse().ok()),
+        None => field.parse().ok().map(|slot| (slot, slot)),
+    };
+    if let Some((start, end)) = range.filter(|(start, end)| start <= end && (*end as usize) < SLOT_COUNT) {
+        node.slots.push((start, end));
+    }
+}
+
+/// Parses the whole `CLUSTER NODES` reply, skipping malformed lines.
+pub(crate) fn parse_cluster_nodes(text: &str) -> Vec<ClusterNode> {
+    // id ip:port@cport[,hostname] flags master ping-sent pong-recv config-epoch link-state slots...
+    text.lines().filter_map(|line| {
+        let items: Vec<&str> = line.split_whitespace().collect();
+        if items.len() < 8 {
+            return None;
+        }
+        let addr: NodeAddr = addr::parse_cluster_addr(items[1])?;
+        let view = ClusterNodeView {
+            id: items[0].to_string(),
+            hostname: addr.hostname.clone(),
+            flags: items[2].split(',').map(str::to_string).collect(),
+            master_id: Some(items[3]).filter(|id| *id != "-").map(str::to_string),
+            link_state: items[7].to_string(),
+        };
+        let mut node = ClusterNode {
+            view,
+            addr,
+            config_epoch: items[6].parse().unwrap_or_default(),
+            slots: Vec::new(),
+            migrations: Vec::new(),
+        };
+        for field in &items[8..] {
+            parse_slot_field(items[0], field, &mut node);
+        }
+        Some(node)
+    }).collect()
+}
+
+pub(crate) async fn get_cluster_nodes(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+) -> Result<Vec<ClusterNode>, CollectError> {
+    let nodes: String = query(pool, instance, con, redis::cmd("CLUSTER").arg("NODES")).await?;
+    let parsed: Vec<ClusterNode> = parse_cluster_nodes(&nodes);
+    if parsed.is_empty() {
+        return Err(CollectError::Parse("CLUSTER NODES returned no nodes".to_string()));
+    }
+    Ok(parsed)
+}
+
+pub(crate) async fn get_cluster_info(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+) -> Result<ClusterHealth, CollectError> {
+    let reply: String = query(pool, instance, con, redis::cmd("CLUSTER").arg("INFO")).await?;
+    let info: InfoMap = parse_redis_info(&reply);
+    let state: String = info.get("cluster_state").cloned()
+        .ok_or_else(|| CollectError::Parse("CLUSTER INFO has no cluster_state".to_string()))?;
+    Ok(ClusterHealth {
+        seed: format!("{}:{}", instance.ip, instance.port),
+        state,
+        slots_assigned: info_num(&info, "cluster_slots_assigned"),
+        slots_ok: info_num(&info, "cluster_slots_ok"),
+        slots_pfail: info_num(&info, "cluster_slots_pfail"),
+        slots_fail: info_num(&info, "cluster_slots_fail"),
+        known_nodes: info_num(&info, "cluster_known_nodes"),
+        size: info_num(&info, "cluster_size"),
+        current_epoch: info_num(&info, "cluster_current_epoch"),
+    })
+}
+
+type NodeTable = Result<Vec<ClusterNode>, CollectError>;
+
+// Migration markers are only printed on the `myself` line, so each master is
+// asked for its own view of the cluster.
+async fn own_view(pool: &ConnectionPool, instance: &RedisInstance) -> NodeTable {
+    let mut con: MultiplexedConnection = pool.get(instance).await?;
+    get_cluster_nodes(pool, instance, &mut con).await
+}
+
+/// Merges the slot ranges of every master with the migrations their own views report.
+fn slot_map(nodes: &[ClusterNode], migrations: Vec<SlotMigration>) -> SlotMap {
+    let mut covered: Vec<bool> = vec![false; SLOT_COUNT];
+    let mut owners: Vec<SlotOwner> = Vec::new();
+    for node in nodes.iter().filter(|n| n.is_master() && !n.slots.is_empty()) {
+        let mut count: u32 = 0;
+        for &(start, end) in &node.slots {
+            covered[start as usize..=end as usize].fill(true);
+            count += (end - start) as u32 + 1;
+        }
+        let addr: RedisInstance = node.instance().unwrap_or_else(|_| node.placeholder());
+        owners.push(SlotOwner {
+            id: node.view.id.clone(),
+            addr: format!("{}:{}", addr.ip, addr.port),
+            ranges: node.slots.clone(),
+            count,
+        });
+    }
+
+    let mut uncovered: Vec<(u16, u16)> = Vec::new();
+    let mut slot: usize = 0;
+    while slot < SLOT_COUNT {
+        if covered[slot] {
+            slot += 1;
+            continue;
+        }
+        let start: usize = slot;
+        while slot < SLOT_COUNT && !covered[slot] {
+            slot += 1;
+        }
+        uncovered.push((start as u16, (slot - 1) as u16));
+    }
+
+    // The source and the target each report their half of the same move.
+    let mut merged: Vec<SlotMigration> = Vec::new();
+    for m in migrations {
+        match merged.iter_mut().find(|x| x.slot == m.slot && x.from == m.from && x.to == m.to) {
+            Some(x) => {
+                x.migrating |= m.migrating;
+                x.importing |= m.importing;
+            }
+            None => merged.push(m),
+        }
+    }
+    merged.sort_by_key(|m| m.slot);
+
+    SlotMap {
+        owners,
+        uncovered,
+        covered: covered.iter().filter(|c| **c).count() as u32,
+        migrations: merged,
+    }
+}
+
+/// Polls every node of a Redis Cluster as listed by `CLUSTER NODES`.
+///
+/// Replicas are attached to their master by node ID rather than read from
+/// the master's INFO, so a replica that lost its link still shows up. Nodes
+/// that cannot be polled (`noaddr`, `handshake`) are reported as down with
+/// the reason; their replicas are still polled and listed under them. Every
+/// report carries the cluster's view of the node.
+///
+/// With `check_consistency`, the view of every master is compared against
+/// the seed's.
+pub(crate) async fn collect_cluster_nodes(
+    pool: &Arc<ConnectionPool>,
+    seed: &RedisInstance,
+    nodes: Vec<ClusterNode>,
+    check_consistency: bool,
+) -> Snapshot {
+    let mut data: Vec<NodeReport> = Vec::new();
+    let mut own_views: Vec<(String, JoinHandle<NodeTable>)> = Vec::new();
+    let mut handles: Vec<(RedisInstance, JoinHandle<Vec<NodeReport>>)> = Vec::new();
+    // Nodes probed on their own, with the master they were listed under, if any
+    let mut orphans: Vec<(RedisInstance, Option<String>, ProbeTask)> = Vec::new();
+    let mut views: HashMap<String, ClusterNodeView> = HashMap::new();
+
+    for master in nodes.iter().filter(|n| n.is_master()) {
+        let master_instance: Option<RedisInstance> = match master.instance() {
+            Ok(instance) => Some(instance),
+            Err(e) => {
+                let mut report: NodeReport = NodeReport::new(&master.placeholder(), "master", None, Err(e));
+                report.cluster = Some(master.view.clone());
+                data.push(report);
+                None
+            }
+        };
+        let shown: RedisInstance = master_instance.clone().unwrap_or_else(|| master.placeholder());
+        let master_addr: String = format!("{}:{}", shown.ip, shown.port);
+        let mut replicas: Vec<RedisInstance> = Vec::new();
+        for replica in nodes.iter().filter(|n| n.is_replica() && n.view.master_id.as_ref() == Some(&master.view.id)) {
+            match replica.instance() {
+                Ok(instance) => {
+                    views.insert(format!("{}:{}", instance.ip, instance.port), replica.view.clone());
+                    if master_instance.is_some() {
+                        replicas.push(instance);
+                    } else {
+                        // The master cannot be polled, but its replicas may still answer.
+                        let pool = pool.clone();
+                        let target: RedisInstance = instance.clone();
+                        orphans.push((instance, Some(master_addr.clone()), tokio::spawn(async move { probe_node(&pool, &target).await })));
+                    }
+                }
+                Err(e) => {
+                    let mut report: NodeReport = NodeReport::new(&replica.placeholder(), "slave", Some(master_addr.clone()), Err(e));
+                    report.cluster = Some(replica.view.clone());
+                    data.push(report);
+                }
+            }
+        }
+        let Some(master_instance) = master_instance else {
+            continue;
+        };
+        views.insert(format!("{}:{}", master_instance.ip, master_instance.port), master.view.clone());
+        let own_pool = pool.clone();
+        let target: RedisInstance = master_instance.clone();
+        own_views.push((
+            format!("{}:{}", master_instance.ip, master_instance.port),
+            tokio::spawn(async move { own_view(&own_pool, &target).await }),
+        ));
+        handles.push((
+            master_instance.clone(),
+            tokio::spawn(collect_master(pool.clone(), master_instance, Some(replicas))),
+        ));
+    }
+
+    // Replicas whose master is not in the table, and nodes that are neither role yet.
+    let known_masters: Vec<&String> = nodes.iter().filter(|n| n.is_master()).map(|n| &n.view.id).collect();
+    for node in nodes.iter().filter(|n| !n.is_master()) {
+        if node.is_replica() && node.view.master_id.as_ref().is_some_and(|id| known_masters.contains(&id)) {
+            continue;
+        }
+        match node.instance() {
+            Ok(instance) => {
+                views.insert(format!("{}:{}", instance.ip, instance.port), node.view.clone());
+                let pool = pool.clone();
+                let target: RedisInstance = instance.clone();
+                orphans.push((instance, None, tokio::spawn(async move { probe_node(&pool, &target).await })));
+            }
+            Err(e) => {
+                let mut report: NodeReport = NodeReport::new(&node.placeholder(), "", None, Err(e));
+                report.cluster = Some(node.view.clone());
+                data.push(report);
+            }
+        }
+    }
+
+    let mut polled: Vec<NodeReport> = Vec::new();
+    for (master, handle) in handles {
+        match handle.await {
+            Ok(reports) => polled.extend(reports),
+            Err(e) => polled.push(NodeReport::new(&master, "master", None, Err(task_failed(e)))),
+        }
+    }
+    for (node, master, handle) in orphans {
+        let probe = handle.await.unwrap_or_else(|e| Err(task_failed(e)));
+        let role: &str = if master.is_some() { "slave" } else { "" };
+        polled.push(NodeReport::new(&node, role, master, probe));
+    }
+    for report in polled.iter_mut() {
+        report.cluster = views.get(&report.addr()).cloned();
+    }
+    polled.extend(data);
+
+    let seed_addr: String = format!("{}:{}", seed.ip, seed.port);
+    let mut migrations: Vec<SlotMigration> = nodes.iter().flat_map(|n| n.migrations.clone()).collect();
+    let mut tables: Vec<(String, Vec<ClusterNode>)> = Vec::new();
+    let mut unreachable: Vec<(String, CollectError)> = Vec::new();
+    for (addr, handle) in own_views {
+        match handle.await.unwrap_or_else(|e| Err(task_failed(e))) {
+            Ok(table) => {
+                migrations.extend(table.iter()
+                    .filter(|n| n.has_flag("myself"))
+                    .flat_map(|n| n.migrations.clone()));
+                if addr != seed_addr {
+                    tables.push((addr, table));
+                }
+            }
+            Err(e) => unreachable.push((addr, e)),
+        }
+    }
+    let slots: SlotMap = slot_map(&nodes, migrations);
+    let consistency: Option<Consistency> = check_consistency.then(|| {
+        tables.insert(0, (seed_addr, nodes));
+        consistency::compare(&tables, &unreachable)
+    });
+    Snapshot { nodes: polled, slots: Some(slots), consistency, ..Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODES: &str = "\
+07c37dfeb235213a872192d90877d0cd55635b91 127.0.0.1:30004@31004,,shard-id=69bc slave e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca 0 1426238317239 4 connected
+67ed2db8d677e59ec4a4cefb06858cf2a1a89fa1 127.0.0.1:30002@31002,redis-2 master - 0 1426238316232 2 connected 5461-10922
+292f8b365bb7edb5e285caf0b7e6ddc7265d2f4f 127.0.0.1:30003@31003 master - 0 1426238318243 3 connected 10923-16383
+6ec23923021cf3ffec47632106199cb7f496ce01 127.0.0.1:30005@31005 slave,fail? 67ed2db8d677e59ec4a4cefb06858cf2a1a89fa1 0 1426238316232 5 disconnected
+824fe116063bc5fcf9f4ffd895bc17aee7731ac3 127.0.0.1:30006@31006 slave 292f8b365bb7edb5e285caf0b7e6ddc7265d2f4f 0 1426238317741 6 connected
+e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca 127.0.0.1:30001@31001 myself,master - 0 0 1 connected 0-5459 5460 [5461-<-67ed2db8d677e59ec4a4cefb06858cf2a1a89fa1] [93->-292f8b365bb7edb5e285caf0b7e6ddc7265d2f4f]
+a1b2c3d4e5f60718293a4b5c6d7e8f9012345678 :0@0 master,fail,noaddr - 1426238300000 1426238299000 7 disconnected
+b1b2c3d4e5f60718293a4b5c6d7e8f9012345678 127.0.0.1:30007@31007 handshake - 0 0 0 connected
+truncated line
+";
+
+    fn node(nodes: &[ClusterNode], id_prefix: &str) -> ClusterNode {
+        nodes.iter().find(|n| n.view.id.starts_with(id_prefix)).cloned().unwrap()
+    }
+
+    fn migration(slot: u16, from: &str, to: &str, migrating: bool, importing: bool) -> SlotMigration {
+        SlotMigration { slot, from: from.to_string(), to: to.to_string(), migrating, importing }
+    }
+
+    #[test]
+    fn every_well_formed_line_is_a_node() {
+        let nodes: Vec<ClusterNode> = parse_cluster_nodes(NODES);
+        assert_eq!(nodes.len(), 8);
+        assert!(parse_cluster_nodes("").is_empty());
+    }
+
+    #[test]
+    fn replicas_point_at_their_master() {
+        let nodes: Vec<ClusterNode> = parse_cluster_nodes(NODES);
+        let replica: ClusterNode = node(&nodes, "07c37d");
+        assert!(replica.is_replica());
+        assert!(!replica.is_master());
+        assert_eq!(replica.view.master_id.as_deref(), Some("e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca"));
+        assert_eq!(replica.view.hostname, None);
+        assert!(replica.slots.is_empty());
+        let instance: RedisInstance = replica.instance().unwrap();
+        assert_eq!((instance.ip.as_str(), instance.port.as_str()), ("127.0.0.1", "30004"));
+
+        let master: ClusterNode = node(&nodes, "67ed2d");
+        assert!(master.is_master());
+        assert_eq!(master.view.master_id, None);
+        assert_eq!(master.view.hostname.as_deref(), Some("redis-2"));
+    }
+
+    #[test]
+    fn failure_flags() {
+        let nodes: Vec<ClusterNode> = parse_cluster_nodes(NODES);
+        let suspect: ClusterNode = node(&nodes, "6ec239");
+        assert_eq!(suspect.view.flags, ["slave", "fail?"]);
+        assert_eq!(suspect.view.link_state, "disconnected");
+        // Suspected nodes keep their address and are still polled
+        assert!(suspect.instance().is_ok());
+
+        let gone: ClusterNode = node(&nodes, "a1b2c3");
+        assert!(gone.is_master() && gone.has_flag("fail") && gone.has_flag("noaddr"));
+        assert!(matches!(gone.instance(), Err(CollectError::ClusterState(_))));
+        assert_eq!(gone.placeholder().port, "0");
+
+        let joining: ClusterNode = node(&nodes, "b1b2c3");
+        assert!(!joining.is_master() && !joining.is_replica());
+        assert!(matches!(joining.instance(), Err(CollectError::ClusterState(_))));
+    }
+
+    #[test]
+    fn single_slots_ranges_and_markers() {
+        let nodes: Vec<ClusterNode> = parse_cluster_nodes(NODES);
+        let myself: ClusterNode = node(&nodes, "e7d1ee");
+        assert!(myself.has_flag("myself"));
+        assert_eq!(myself.slots, [(0, 5459), (5460, 5460)]);
+        assert_eq!(myself.migrations, [
+            migration(5461, "67ed2db8d677e59ec4a4cefb06858cf2a1a89fa1", "e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca", false, true),
+            migration(93, "e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca", "292f8b365bb7edb5e285caf0b7e6ddc7265d2f4f", true, false),
+        ]);
+    }
+
+    #[test]
+    fn malformed_slot_fields_are_skipped() {
+        let mut node: ClusterNode = parse_cluster_nodes(NODES).remove(0);
+        for field in ["10-5", "16384", "0-16384", "x", "[12]", "[x->-abc]"] {
+            parse_slot_field("id", field, &mut node);
+        }
+        assert!(node.slots.is_empty());
+        assert!(node.migrations.is_empty());
+        parse_slot_field("id", "16383", &mut node);
+        assert_eq!(node.slots, [(16383, 16383)]);
+    }
+
+    #[test]
+    fn slot_map_reports_owners_and_gaps() {
+        let nodes: Vec<ClusterNode> = parse_cluster_nodes(NODES);
+        let map: SlotMap = slot_map(&nodes, Vec::new());
+        assert_eq!(map.covered, SLOT_COUNT as u32);
+        assert!(map.uncovered.is_empty());
+        let counts: Vec<(&str, &str, u32)> = map.owners.iter().map(|o| (&o.id[..6], o.addr.as_str(), o.count)).collect();
+        assert_eq!(counts, [("67ed2d", "127.0.0.1:30002", 5462), ("292f8b", "127.0.0.1:30003", 5461), ("e7d1ee", "127.0.0.1:30001", 5461)]);
+
+        // Slots of a master that is not listed are uncovered
+        let partial: Vec<ClusterNode> = nodes.into_iter().filter(|n| !n.view.id.starts_with("292f8b")).collect();
+        let map: SlotMap = slot_map(&partial, Vec::new());
+        assert_eq!(map.uncovered, [(10923, 16383)]);
+        assert_eq!(map.covered, 10923);
+
+        // Slots on a replica line do not count
+        let text: &str = "\
+aaa 127.0.0.1:1@2 master - 0 0 1 connected 100-199 300
+bbb 127.0.0.1:3@4 slave aaa 0 0 1 connected 0-16383
+";
+        let map: SlotMap = slot_map(&parse_cluster_nodes(text), Vec::new());
+        assert_eq!(map.uncovered, [(0, 99), (200, 299), (301, 16383)]);
+        assert_eq!(map.covered, 101);
+    }
+
+    #[test]
+    fn both_halves_of_a_migration_are_merged() {
+        let migrations: Vec<SlotMigration> = vec![
+            migration(93, "a", "b", true, false),
+            migration(7, "c", "a", false, true),
+            migration(93, "a", "b", false, true),
+        ];
+        let map: SlotMap = slot_map(&[], migrations);
+        assert_eq!(map.migrations, [migration(7, "c", "a", false, true), migration(93, "a", "b", true, true)]);
+        assert_eq!(map.uncovered, [(0, 16383)]);
+    }
+}
@@ -0,0 +1,936 @@
+use redis::{aio::MultiplexedConnection, FromRedisValue, Value};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::broadcast, task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{
+    addr,
+    alerts::{Alert, AlertTracker, Alerting},
+    clients::{self, ClientStore, ClientSummary},
+    cluster::{self, ClusterHealth, ClusterNode, ClusterNodeView, SlotMap},
+    commandstats::{self, CommandStat, ErrorStat},
+    consistency::Consistency,
+    error::{CollectError, ErrorReport},
+    events::{Event, EventLog},
+    history::{unix_millis, History, Sample},
+    latency::{self, CommandLatency, LatencyEvent, LatencyLog, LatencySpike},
+    metrics,
+    persistence,
+    pool::ConnectionPool,
+    replication::{self, ReplicaStatus},
+    sentinel::{self, SentinelReport},
+    slowlog::{self, SlowEntry, SlowLog, SlowQuery},
+    ClusterMode, RedisCluster, RedisConfig, RedisInstance,
+};
+
+// Number of snapshots a slow subscriber may fall behind before it starts skipping.
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Everything collected for one cluster on one tick.
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Snapshot {
+    pub(crate) nodes: Vec<NodeReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) sentinels: Vec<SentinelReport>,
+    /// Slot coverage, in Redis Cluster mode only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) slots: Option<SlotMap>,
+    /// `CLUSTER INFO` of the seed, in Redis Cluster mode only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cluster_info: Option<ClusterHealth>,
+    /// Disagreements between the masters' views, when the check is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) consistency: Option<Consistency>,
+    /// Events detected on this tick.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) events: Vec<Event>,
+    /// Latency spikes reported by the nodes since the previous tick.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) latency_spikes: Vec<LatencySpike>,
+    /// Slow queries logged by the nodes since the previous tick.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) slow_queries: Vec<SlowQuery>,
+    /// Pending and firing alerts after this tick.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) alerts: Vec<Alert>,
+}
+
+pub(crate) type InfoMap = HashMap<String, String>;
+pub(crate) type ProbeTask = JoinHandle<Result<NodeProbe, CollectError>>;
+
+/// Everything read from a reachable node on one tick.
+#[derive(Debug, Default)]
+pub(crate) struct NodeProbe {
+    pub(crate) info: InfoMap,
+    pub(crate) commands: Vec<CommandStat>,
+    pub(crate) errors: Vec<ErrorStat>,
+    pub(crate) latency_stats: Vec<CommandLatency>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum NodeStatus {
+    Up,
+    Loading,
+    Timeout,
+    Down,
+}
+
+impl NodeStatus {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            NodeStatus::Up => "up",
+            NodeStatus::Loading => "loading",
+            NodeStatus::Timeout => "timeout",
+            NodeStatus::Down => "down",
+        }
+    }
+}
+
+/// One node as seen on a single tick, reachable or not.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct NodeReport {
+    pub(crate) ip: String,
+    pub(crate) port: String,
+    /// Role reported by INFO, or the role the node was discovered under when it is down.
+    pub(crate) role: String,
+    /// `ip:port` of the master a replica was discovered from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) master: Option<String>,
+    pub(crate) status: NodeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<ErrorReport>,
+    pub(crate) info: HashMap<String, String>,
+    /// The INFO Keyspace section, per database.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) keyspace: Vec<DbKeyspace>,
+    /// Replicas listed in a master's INFO with their offset and lag.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) replicas: Vec<ReplicaLink>,
+    /// The node's row in `CLUSTER NODES`, in cluster mode only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) cluster: Option<ClusterNodeView>,
+    /// `INFO commandstats`, busiest command first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) commands: Vec<CommandStat>,
+    /// `INFO errorstats`, Redis 6.2 and later.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) errors: Vec<ErrorStat>,
+    /// `INFO latencystats` percentiles per command, Redis 7 and later.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) latency_stats: Vec<CommandLatency>,
+    /// `LATENCY LATEST`, empty unless the latency monitor is enabled.
+    ///
+    /// Read after the node was polled, see `collect_diagnostics`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) latency: Vec<LatencyEvent>,
+    /// `SLOWLOG GET` entries not logged yet, only sent to the dashboard through
+    /// the cluster's slow query log.
+    #[serde(skip)]
+    pub(crate) slowlog: Vec<SlowEntry>,
+    /// Numeric top-level fields of `MEMORY STATS`, e.g. `overhead.total` or `fragmentation.bytes`,
+    /// as last read on the node's memory interval.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) memory_stats: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) memory_doctor: Option<String>,
+    /// Failed saves and, when configured, stale RDB snapshots.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) persistence_warnings: Vec<String>,
+    /// Link state and byte lag of a reachable replica.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) replication: Option<ReplicaStatus>,
+    /// Aggregates of `CLIENT LIST`, only on the tick after a new sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) clients: Option<ClientSummary>,
+    /// Rates and gauges derived on the server, absent while the node is unreachable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sample: Option<Sample>,
+}
+
+impl NodeReport {
+    pub(crate) fn addr(&self) -> String {
+        format!("{}:{}", self.ip, self.port)
+    }
+
+    pub(crate) fn new(
+        instance: &RedisInstance,
+        role: &str,
+        master: Option<String>,
+        probe: Result<NodeProbe, CollectError>,
+    ) -> Self {
+        let (status, error, probe) = match probe {
+            Ok(probe) if probe.info.get("loading").is_some_and(|v| v == "1") => {
+                (NodeStatus::Loading, Some(ErrorReport::from(&CollectError::Loading)), probe)
+            }
+            Ok(probe) => (NodeStatus::Up, None, probe),
+            Err(e @ CollectError::Loading) => (NodeStatus::Loading, Some(ErrorReport::from(&e)), NodeProbe::default()),
+            Err(e @ CollectError::Timeout(_)) => (NodeStatus::Timeout, Some(ErrorReport::from(&e)), NodeProbe::default()),
+            Err(e) => (NodeStatus::Down, Some(ErrorReport::from(&e)), NodeProbe::default()),
+        };
+        let NodeProbe { info, commands, errors, latency_stats } = probe;
+        NodeReport {
+            ip: instance.ip.clone(),
+            port: instance.port.clone(),
+            role: info.get("role").cloned().unwrap_or_else(|| role.to_string()),
+            master,
+            status,
+            error,
+            replicas: get_replicas(&info),
+            keyspace: get_keyspace(&info),
+            info,
+            cluster: None,
+            commands,
+            errors,
+            latency_stats,
+            latency: Vec::new(),
+            slowlog: Vec::new(),
+            memory_stats: BTreeMap::new(),
+            memory_doctor: None,
+            persistence_warnings: Vec::new(),
+            replication: None,
+            clients: None,
+            sample: None,
+        }
+    }
+}
+
+/// Background poller for a single cluster.
+///
+/// One task per cluster walks the topology once per interval and fans the
+/// resulting snapshot out to every subscribed SSE client.
+#[derive(Clone)]
+pub(crate) struct Collector {
+    tx: broadcast::Sender<Arc<Snapshot>>,
+    history: Arc<History>,
+    events: Arc<EventLog>,
+    alerts: Arc<AlertTracker>,
+    latency: Arc<LatencyLog>,
+    slowlog: Arc<SlowLog>,
+    clients: Arc<ClientStore>,
+    latest: Arc<Mutex<Arc<Snapshot>>>,
+}
+
+impl Collector {
+    pub(crate) fn spawn(cluster: RedisCluster, config: &RedisConfig, alerting: Arc<Alerting>, period: Duration) -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let sender = tx.clone();
+        let pool: Arc<ConnectionPool> = Arc::new(ConnectionPool::new(&cluster, config));
+        let sentinel_pool: Arc<ConnectionPool> = Arc::new(ConnectionPool::for_sentinels(&cluster, config));
+        let history: Arc<History> = Arc::new(History::default());
+        let recorder = history.clone();
+        let events: Arc<EventLog> = Arc::new(EventLog::default());
+        let event_log = events.clone();
+        let alerts: Arc<AlertTracker> = Arc::new(AlertTracker::new(alerting.clone(), &cluster.name));
+        let tracker = alerts.clone();
+        let latency: Arc<LatencyLog> = Arc::new(LatencyLog::default());
+        let latency_log = latency.clone();
+        let slowlog: Arc<SlowLog> = Arc::new(SlowLog::new(cluster.slowlog_redact));
+        let slow_log = slowlog.clone();
+        let latest: Arc<Mutex<Arc<Snapshot>>> = Arc::default();
+        let last = latest.clone();
+        let clients: Arc<ClientStore> = Arc::default();
+        let client_store = clients.clone();
+        if cluster.client_list_interval_secs > 0 {
+            let period: Duration = Duration::from_secs(cluster.client_list_interval_secs);
+            // On its own connections, so a long CLIENT LIST never holds up INFO on the shared ones.
+            let sampler_pool: Arc<ConnectionPool> = Arc::new(ConnectionPool::new(&cluster, config));
+            clients::spawn_sampler(sampler_pool, latest.clone(), clients.clone(), period);
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // When the client summary of each node was last sent
+            let mut clients_sent: HashMap<String, u64> = HashMap::new();
+            let mut memory: MemoryReadings = MemoryReadings::new(Duration::from_secs(cluster.memory_interval_secs));
+            loop {
+                interval.tick().await;
+                pool.evict_idle();
+                sentinel_pool.evict_idle();
+                let seed = cluster.clone();
+                let diagnostic_pool = pool.clone();
+                let pool = pool.clone();
+                let sentinel_pool = sentinel_pool.clone();
+                match tokio::spawn(async move { collect_cluster(&pool, &sentinel_pool, &seed).await }).await {
+                    // No subscribers is not an error; the snapshot is simply dropped.
+                    Ok(mut data) => {
+                        collect_diagnostics(&diagnostic_pool, &mut data.nodes, &latency_log, &slow_log, &mut memory, unix_millis()).await;
+                        let ts: u64 = unix_millis();
+                        replication::link(&mut data.nodes);
+                        recorder.observe(&mut data.nodes, ts);
+                        data.events = event_log.observe(&data.nodes, ts);
+                        data.latency_spikes = latency_log.observe(&data.nodes);
+                        data.slow_queries = slow_log.observe(&data.nodes);
+                        for report in data.nodes.iter_mut() {
+                            report.persistence_warnings = persistence::warnings(report, cluster.max_save_age_secs, ts);
+                            let node: String = report.addr();
+                            if let Some(at) = client_store.sampled_at(&node)
+                                && clients_sent.get(&node) != Some(&at) {
+                                report.clients = client_store.summary(&node);
+                                clients_sent.insert(node, at);
+                            }
+                        }
+                        clients_sent.retain(|node, _| data.nodes.iter().any(|r| r.addr() == *node));
+                        alerting.notify(&cluster.name, tracker.evaluate(&data.nodes, ts));
+                        data.alerts = tracker.active();
+                        metrics::observe(&cluster.name, &data.nodes);
+                        let data: Arc<Snapshot> = Arc::new(data);
+                        *last.lock().unwrap() = data.clone();
+                        let _ = sender.send(data);
+                    }
+                    Err(e) => eprintln!("Collector for cluster {} failed: {}", cluster.name, e),
+                }
+            }
+        });
+        Collector { tx, history, events, alerts, latency, slowlog, clients, latest }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Arc<Snapshot>> {
+        self.tx.subscribe()
+    }
+
+    pub(crate) fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub(crate) fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    pub(crate) fn alerts(&self) -> &AlertTracker {
+        &self.alerts
+    }
+
+    pub(crate) fn latency(&self) -> &LatencyLog {
+        &self.latency
+    }
+
+    pub(crate) fn slowlog(&self) -> &SlowLog {
+        &self.slowlog
+    }
+
+    pub(crate) fn clients(&self) -> &ClientStore {
+        &self.clients
+    }
+
+    /// The most recent snapshot, empty until the first tick completes.
+    pub(crate) fn latest(&self) -> Arc<Snapshot> {
+        self.latest.lock().unwrap().clone()
+    }
+}
+
+/// Runs `cmd` on `instance`, bounded by the node's command timeout.
+///
+/// A node that does not answer in time has its connection dropped so that
+/// the next tick starts from a fresh connect.
+pub(crate) async fn query<T: FromRedisValue>(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+    cmd: &redis::Cmd,
+) -> Result<T, CollectError> {
+    query_within(pool, instance, con, cmd, pool.timeouts(instance).command).await
+}
+
+/// Like [`query`], for commands that are expected to take longer than the command timeout.
+pub(crate) async fn query_within<T: FromRedisValue>(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+    cmd: &redis::Cmd,
+    limit: Duration,
+) -> Result<T, CollectError> {
+    match tokio::time::timeout(limit, cmd.query_async(con)).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => {
+            pool.invalidate(instance, &e);
+            Err(CollectError::from(e))
+        }
+        Err(_) => {
+            pool.discard(instance);
+            Err(CollectError::Timeout(format!("no reply within {} ms", limit.as_millis())))
+        }
+    }
+}
+
+/// Runs a diagnostic command the node may refuse or be too busy for.
+///
+/// Any failure only means it is not available this tick: the node keeps
+/// the status its INFO gave it, and a late reply does not cost the shared
+/// connection, which is only dropped when it is actually broken.
+pub(crate) async fn diagnostic<T: FromRedisValue>(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+    cmd: &redis::Cmd,
+) -> Option<T> {
+    let limit: Duration = pool.timeouts(instance).command;
+    match tokio::time::timeout(limit, cmd.query_async(con)).await {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(e)) => {
+            pool.invalidate(instance, &e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+async fn get_redis_info(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+) -> Result<String, CollectError> {
+    query(pool, instance, con, &redis::cmd("INFO")).await
+}
+
+pub(crate) fn parse_redis_info(info: &str) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    for line in info.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            map.insert(key.to_string(), value.to_string());
+        }
+    }
+    map
+}
+
+/// A replica as seen from its master's `slaveN` INFO line.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ReplicaLink {
+    pub(crate) ip: String,
+    pub(crate) port: String,
+    pub(crate) state: String,
+    pub(crate) offset: u64,
+    /// Seconds since the replica last acknowledged the stream
+    pub(crate) lag: u64,
+}
+
+impl ReplicaLink {
+    pub(crate) fn instance(&self) -> RedisInstance {
+        RedisInstance { ip: self.ip.clone(), port: self.port.clone(), ..Default::default() }
+    }
+}
+
+fn parse_replica_link(line: &str) -> Option<ReplicaLink> {
+    // slave$i=ip, port, state, offset, lag
+    let mut ip = None;
+    let mut port = None;
+    let mut state = String::new();
+    let mut offset: u64 = 0;
+    let mut lag: u64 = 0;
+    for item in line.split(',') {
+        if let Some((k, v)) = item.split_once('=') {
+            match k {
+                "ip" => ip = Some(addr::normalize_host(v).to_string()),
+                "port" => port = Some(v.to_string()),
+                "state" => state = v.to_string(),
+                "offset" => offset = v.parse().unwrap_or_default(),
+                "lag" => lag = v.parse().unwrap_or_default(),
+                _ => {}
+            }
+        }
+    }
+    Some(ReplicaLink { ip: ip?, port: port?, state, offset, lag })
+}
+
+/// Every `slaveN` entry of a master's INFO, in index order.
+fn get_replicas(info_map: &HashMap<String, String>) -> Vec<ReplicaLink> {
+    let mut indexed: Vec<(u32, ReplicaLink)> = info_map.iter()
+        .filter_map(|(key, value)| {
+            let index: u32 = key.strip_prefix("slave")?.parse().ok()?;
+            Some((index, parse_replica_link(value)?))
+        })
+        .collect();
+    indexed.sort_by_key(|(index, _)| *index);
+    indexed.into_iter().map(|(_, link)| link).collect()
+}
+
+/// A `dbN` line of the INFO Keyspace section.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct DbKeyspace {
+    pub(crate) db: u32,
+    pub(crate) keys: u64,
+    pub(crate) expires: u64,
+    /// Milliseconds, sampled by Redis; 0 when no key has a TTL.
+    pub(crate) avg_ttl: u64,
+}
+
+/// Every `dbN` entry of INFO, in database order.
+fn get_keyspace(info_map: &HashMap<String, String>) -> Vec<DbKeyspace> {
+    let mut dbs: Vec<DbKeyspace> = info_map.iter()
+        .filter_map(|(key, value)| {
+            let db: u32 = key.strip_prefix("db")?.parse().ok()?;
+            let fields: HashMap<&str, u64> = value.split(',')
+                .filter_map(|item| item.split_once('='))
+                .filter_map(|(k, v)| Some((k, v.trim().parse().ok()?)))
+                .collect();
+            Some(DbKeyspace {
+                db,
+                keys: *fields.get("keys")?,
+                expires: fields.get("expires").copied().unwrap_or_default(),
+                avg_ttl: fields.get("avg_ttl").copied().unwrap_or_default(),
+            })
+        })
+        .collect();
+    dbs.sort_by_key(|d| d.db);
+    dbs
+}
+
+// Sections of `INFO all` that are parsed on their own rather than kept as INFO fields.
+const STAT_SECTIONS: [&str; 3] = ["Commandstats", "Errorstats", "Latencystats"];
+
+// Splits an `INFO all` reply into the plain INFO fields and the statistics sections.
+fn split_stat_sections(reply: &str) -> (String, String) {
+    let mut info: String = String::new();
+    let mut stats: String = String::new();
+    let mut in_stats: bool = false;
+    for line in reply.lines() {
+        if let Some(section) = line.strip_prefix("# ") {
+            in_stats = STAT_SECTIONS.contains(&section.trim());
+        }
+        let out: &mut String = if in_stats { &mut stats } else { &mut info };
+        out.push_str(line);
+        out.push('\n');
+    }
+    (info, stats)
+}
+
+/// Reads INFO plus the command, error and latency statistics, which a bare INFO leaves out.
+///
+/// Everything comes from one `INFO all` round trip, so the statistics cannot
+/// fail a node whose INFO was read; sections a node does not know yet
+/// (errorstats before Redis 6.2, latencystats before 7) are simply absent.
+pub(crate) async fn probe_node(pool: &ConnectionPool, instance: &RedisInstance) -> Result<NodeProbe, CollectError> {
+    let mut con: MultiplexedConnection = pool.get(instance).await?;
+    let reply: String = query(pool, instance, &mut con, redis::cmd("INFO").arg("all")).await?;
+    let (info, stats) = split_stat_sections(&reply);
+    Ok(NodeProbe {
+        info: parse_redis_info(&info),
+        commands: commandstats::parse_commandstats(&stats),
+        errors: commandstats::parse_errorstats(&stats),
+        latency_stats: latency::parse_latencystats(&stats),
+    })
+}
+
+// What is read from one reachable node after it was polled.
+struct DiagnosticPlan {
+    instance: RedisInstance,
+    // Newest spike logged per latency event, `None` on the first poll of the node
+    latency_seen: Option<HashMap<String, u64>>,
+    slowlog_last: Option<u64>,
+    // Whether `MEMORY STATS` and `MEMORY DOCTOR` are due
+    memory: bool,
+}
+
+#[derive(Default)]
+struct Diagnostics {
+    latency: Vec<LatencyEvent>,
+    slowlog: Vec<SlowEntry>,
+    memory: Option<MemoryReading>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MemoryReading {
+    ts: u64,
+    stats: BTreeMap<String, f64>,
+    doctor: Option<String>,
+}
+
+/// The last `MEMORY STATS` and `MEMORY DOCTOR` of every node of a cluster.
+///
+/// Both change slowly, the doctor report is advisory text, so they are
+/// read once per interval rather than on every tick; `0` disables them.
+pub(crate) struct MemoryReadings {
+    period_ms: u64,
+    nodes: HashMap<String, MemoryReading>,
+}
+
+impl MemoryReadings {
+    pub(crate) fn new(period: Duration) -> Self {
+        MemoryReadings { period_ms: period.as_millis() as u64, nodes: HashMap::new() }
+    }
+
+    fn due(&self, node: &str, ts: u64) -> bool {
+        self.period_ms > 0 && self.nodes.get(node).is_none_or(|m| ts.saturating_sub(m.ts) >= self.period_ms)
+    }
+}
+
+async fn probe_diagnostics(pool: &ConnectionPool, plan: DiagnosticPlan) -> Diagnostics {
+    let mut found = Diagnostics::default();
+    let instance: &RedisInstance = &plan.instance;
+    let Ok(mut con) = pool.get(instance).await else {
+        return found;
+    };
+    let latest: Option<Value> = diagnostic(pool, instance, &mut con, redis::cmd("LATENCY").arg("LATEST")).await;
+    found.latency = latest.as_ref().map(latency::parse_latency_latest).unwrap_or_default();
+    for event in found.latency.iter_mut() {
+        // The history only holds something new when the latest spike is.
+        let logged: Option<u64> = plan.latency_seen.as_ref().map(|seen| seen.get(&event.event).copied().unwrap_or_default());
+        if logged.is_some_and(|ts| event.ts <= ts) {
+            continue;
+        }
+        let history: Option<Value> = diagnostic(pool, instance, &mut con, redis::cmd("LATENCY").arg("HISTORY").arg(&event.event)).await;
+        event.history = history.as_ref().map(latency::parse_latency_history).unwrap_or_default();
+    }
+    found.slowlog = slowlog::read_slowlog(pool, instance, &mut con, plan.slowlog_last).await;
+    if plan.memory {
+        let stats: Option<Value> = diagnostic(pool, instance, &mut con, redis::cmd("MEMORY").arg("STATS")).await;
+        found.memory = Some(MemoryReading {
+            ts: unix_millis(),
+            stats: stats.as_ref().map(parse_memory_stats).unwrap_or_default(),
+            doctor: diagnostic(pool, instance, &mut con, redis::cmd("MEMORY").arg("DOCTOR")).await,
+        });
+    }
+    found
+}
+
+/// Reads the latency events, new slow queries and, when due, the memory
+/// reports of every reachable node of a snapshot, concurrently.
+///
+/// `LATENCY HISTORY` is only asked for events with a spike the log has not
+/// seen yet, or on the first poll of a node to backfill it. Reachable nodes
+/// carry their last memory reading on every tick.
+pub(crate) async fn collect_diagnostics(
+    pool: &Arc<ConnectionPool>,
+    reports: &mut [NodeReport],
+    latency: &LatencyLog,
+    slowlog: &SlowLog,
+    memory: &mut MemoryReadings,
+    ts: u64,
+) {
+    let handles: Vec<(usize, JoinHandle<Diagnostics>)> = reports.iter().enumerate()
+        .filter(|(_, r)| r.status == NodeStatus::Up)
+        .map(|(i, r)| {
+            let plan = DiagnosticPlan {
+                instance: RedisInstance { ip: r.ip.clone(), port: r.port.clone(), ..Default::default() },
+                latency_seen: latency.seen(&r.addr()),
+                slowlog_last: slowlog.last_id(r),
+                memory: memory.due(&r.addr(), ts),
+            };
+            let pool = pool.clone();
+            (i, tokio::spawn(async move { probe_diagnostics(&pool, plan).await }))
+        })
+        .collect();
+    for (i, handle) in handles {
+        let Ok(found) = handle.await else {
+            continue;
+        };
+        reports[i].latency = found.latency;
+        reports[i].slowlog = found.slowlog;
+        if let Some(reading) = found.memory {
+            memory.nodes.insert(reports[i].addr(), reading);
+        }
+    }
+    memory.nodes.retain(|node, _| reports.iter().any(|r| r.addr() == *node));
+    for report in reports.iter_mut().filter(|r| r.status == NodeStatus::Up) {
+        if let Some(reading) = memory.nodes.get(&report.addr()) {
+            report.memory_stats = reading.stats.clone();
+            report.memory_doctor = reading.doctor.clone();
+        }
+    }
+}
+
+/// Flattens the numeric fields of `MEMORY STATS`, a list of name/value pairs.
+///
+/// Nested entries such as the per-database `db.N` overheads are skipped.
+fn parse_memory_stats(reply: &Value) -> BTreeMap<String, f64> {
+    let fields: &[Value] = match reply {
+        Value::Bulk(fields) => fields,
+        _ => &[],
+    };
+    fields.chunks_exact(2)
+        .filter_map(|pair| {
+            let name: String = String::from_redis_value(&pair[0]).ok()?;
+            let value: f64 = match &pair[1] {
+                Value::Int(n) => *n as f64,
+                Value::Data(bytes) => std::str::from_utf8(bytes).ok()?.parse().ok()?,
+                _ => return None,
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+enum Discovery {
+    Masters(Vec<RedisInstance>),
+    // The seed is a replica of `master`.
+    Replica { master: RedisInstance },
+    Cluster {
+        nodes: Vec<ClusterNode>,
+        health: Option<ClusterHealth>,
+    },
+    // The seeds are Sentinels, which are walked by `sentinel::collect_sentinel`.
+    Sentinel,
+}
+
+/// Asks a single seed which nodes make up the cluster.
+async fn discover_masters(pool: &ConnectionPool, seed: &RedisInstance) -> Result<Discovery, CollectError> {
+    let mut con: MultiplexedConnection = pool.get(seed).await?;
+    let info: String = get_redis_info(pool, seed, &mut con).await?;
+    let info_map: HashMap<String, String> = parse_redis_info(&info);
+
+    if info_map.get("redis_mode").is_some_and(|v| v == "sentinel") {
+        return Ok(Discovery::Sentinel);
+    }
+    let cluster_enabled: bool = info_map.get("cluster_enabled")
+        .map(|v| v == "1").unwrap_or(false);
+    if cluster_enabled {
+        let nodes: Vec<ClusterNode> = cluster::get_cluster_nodes(pool, seed, &mut con).await?;
+        // The node table is enough to poll the cluster; health is best effort.
+        let health: Option<ClusterHealth> = cluster::get_cluster_info(pool, seed, &mut con).await.ok();
+        return Ok(Discovery::Cluster { nodes, health });
+    }
+
+    match info_map.get("role").map(|v| v.as_str()) {
+        Some("master") => Ok(Discovery::Masters(vec![seed.clone()])),
+        Some("slave") => {
+            let master: RedisInstance = addr::instance(
+                info_map.get("master_host").map(|v| v.as_str()).unwrap_or_default(),
+                info_map.get("master_port").map(|v| v.as_str()).unwrap_or_default(),
+            ).ok_or_else(|| CollectError::Parse("replica INFO has no master_host/master_port".to_string()))?;
+            Ok(Discovery::Replica { master })
+        }
+        Some(role) => Err(CollectError::UnsupportedRole(role.to_string())),
+        None => Err(CollectError::Parse("INFO reply has no role field".to_string())),
+    }
+}
+
+/// Walks the whole topology reachable from the configured seeds.
+///
+/// Seeds are tried in order until one can describe the topology; masters are
+/// then polled concurrently, each on its own task, so a slow node only delays
+/// itself. When no seed answers, every seed is reported as down.
+async fn collect_cluster(
+    pool: &Arc<ConnectionPool>,
+    sentinel_pool: &Arc<ConnectionPool>,
+    cluster: &RedisCluster,
+) -> Snapshot {
+    if cluster.mode == ClusterMode::Sentinel {
+        return sentinel::collect_sentinel(pool, sentinel_pool, cluster).await;
+    }
+
+    let mut seed_errors: Vec<NodeReport> = Vec::new();
+    let mut masters: Option<Vec<RedisInstance>> = None;
+    // The replica that answered discovery, with the master it named
+    let mut replica_seed: Option<(RedisInstance, RedisInstance)> = None;
+    for seed in &cluster.instances {
+        let discovered: Result<Discovery, CollectError> = match discover_masters(pool, seed).await {
+            // A Sentinel with a password of its own refuses the data nodes' one.
+            Err(CollectError::AuthFailed(_)) if cluster.sentinel_password.is_some() => {
+                discover_masters(sentinel_pool, seed).await
+            }
+            discovered => discovered,
+        };
+        match discovered {
+            Ok(Discovery::Masters(found)) => {
+                masters = Some(found);
+                break;
+            }
+            Ok(Discovery::Replica { master }) => {
+                masters = Some(vec![master.clone()]);
+                replica_seed = Some((seed.clone(), master));
+                break;
+            }
+            Ok(Discovery::Cluster { nodes, health }) => {
+                let mut snapshot: Snapshot = cluster::collect_cluster_nodes(pool, seed, nodes, cluster.check_consistency).await;
+                snapshot.cluster_info = health;
+                return snapshot;
+            }
+            Ok(Discovery::Sentinel) => return sentinel::collect_sentinel(pool, sentinel_pool, cluster).await,
+            Err(e) => seed_errors.push(NodeReport::new(seed, "", None, Err(e))),
+        }
+    }
+    let Some(masters) = masters else {
+        return Snapshot { nodes: seed_errors, ..Default::default() };
+    };
+
+    let handles: Vec<(RedisInstance, JoinHandle<Vec<NodeReport>>)> = masters.into_iter()
+        .map(|master| (master.clone(), tokio::spawn(collect_master(pool.clone(), master, None))))
+        .collect();
+    let mut nodes: Vec<NodeReport> = Vec::new();
+    for (master, handle) in handles {
+        match handle.await {
+            Ok(reports) => nodes.extend(reports),
+            Err(e) => nodes.push(NodeReport::new(&master, "master", None, Err(task_failed(e)))),
+        }
+    }
+    if let Some((seed, master)) = replica_seed {
+        nodes.extend(unlisted_replicas(pool, &cluster.instances, &seed, &master, &nodes).await);
+    }
+    Snapshot { nodes, ..Default::default() }
+}
+
+/// Replicas among the seeds that their master's INFO did not list, e.g. while
+/// the master is down during a failover.
+///
+/// The replica that answered discovery is always reported; other seeds only
+/// when their INFO names the same master. A seed configured by hostname that
+/// the master lists by IP is recognised by its run ID.
+async fn unlisted_replicas(
+    pool: &Arc<ConnectionPool>,
+    seeds: &[RedisInstance],
+    seed: &RedisInstance,
+    master: &RedisInstance,
+    listed: &[NodeReport],
+) -> Vec<NodeReport> {
+    let same = |a: &RedisInstance, b: &RedisInstance| a.ip == b.ip && a.port == b.port;
+    let is_listed = |instance: &RedisInstance| listed.iter().any(|r| r.ip == instance.ip && r.port == instance.port);
+    let master_addr: String = format!("{}:{}", master.ip, master.port);
+    let handles: Vec<(RedisInstance, ProbeTask)> = std::iter::once(seed)
+        .chain(seeds.iter().filter(|s| !same(s, seed)))
+        .filter(|s| !is_listed(s))
+        .map(|s| {
+            let pool = pool.clone();
+            let target: RedisInstance = s.clone();
+            (s.clone(), tokio::spawn(async move { probe_node(&pool, &target).await }))
+        })
+        .collect();
+
+    let mut found: Vec<NodeReport> = Vec::new();
+    for (instance, handle) in handles {
+        let probe: Result<NodeProbe, CollectError> = handle.await.unwrap_or_else(|e| Err(task_failed(e)));
+        if let Ok(p) = &probe {
+            let run_id: Option<&String> = p.info.get("run_id");
+            if run_id.is_some() && listed.iter().chain(&found).any(|r| r.info.get("run_id") == run_id) {
+                continue;
+            }
+            let follows: Option<RedisInstance> = addr::instance(
+                p.info.get("master_host").map(|v| v.as_str()).unwrap_or_default(),
+                p.info.get("master_port").map(|v| v.as_str()).unwrap_or_default(),
+            );
+            if !same(&instance, seed) && !follows.is_some_and(|f| same(&f, master)) {
+                continue;
+            }
+        } else if !same(&instance, seed) {
+            continue;
+        }
+        found.push(NodeReport::new(&instance, "slave", Some(master_addr.clone()), probe));
+    }
+    found
+}
+
+/// Polls a master and its replicas concurrently.
+///
+/// Replicas are taken from the master's INFO unless the caller already knows
+/// them, e.g. from Sentinel.
+pub(crate) async fn collect_master(
+    pool: Arc<ConnectionPool>,
+    master: RedisInstance,
+    known_replicas: Option<Vec<RedisInstance>>,
+) -> Vec<NodeReport> {
+    let master_probe = probe_node(&pool, &master).await;
+    let master_addr: String = format!("{}:{}", master.ip, master.port);
+
+    let master_report: NodeReport = NodeReport::new(&master, "master", None, master_probe);
+    let replicas: Vec<RedisInstance> = match known_replicas {
+        Some(replicas) => replicas,
+        None => master_report.replicas.iter().map(ReplicaLink::instance).collect(),
+    };
+    let mut handles: Vec<(RedisInstance, ProbeTask)> = Vec::new();
+    for replica in replicas {
+        let pool = pool.clone();
+        let target: RedisInstance = replica.clone();
+        handles.push((replica, tokio::spawn(async move { probe_node(&pool, &target).await })));
+    }
+
+    let mut data: Vec<NodeReport> = vec![master_report];
+    for (replica, handle) in handles {
+        let probe = handle.await.unwrap_or_else(|e| Err(task_failed(e)));
+        data.push(NodeReport::new(&replica, "slave", Some(master_addr.clone()), probe));
+    }
+    data
+}
+
+pub(crate) fn task_failed(e: tokio::task::JoinError) -> CollectError {
+    CollectError::Redis(format!("collector task failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO_ALL: &str = "# Server\r\nredis_version:7.2.4\r\n\r\n\
+        # Commandstats\r\ncmdstat_get:calls=10,usec=30,usec_per_call=3.00,rejected_calls=0,failed_calls=0\r\n\r\n\
+        # Latencystats\r\n\
+        latency_percentiles_usec_get:p50=1.003,p99=5.023,p99.9=11.007\r\n\
+        latency_percentiles_usec_set:p50=2.001,p99=9.087,p99.9=20.031\r\n\r\n\
+        # Keyspace\r\ndb0:keys=22,expires=3,avg_ttl=1000\r\n";
+
+    #[test]
+    fn statistics_sections_are_kept_out_of_the_info_fields() {
+        let (info, stats) = split_stat_sections(INFO_ALL);
+        let info: InfoMap = parse_redis_info(&info);
+        assert_eq!(info.get("redis_version").map(String::as_str), Some("7.2.4"));
+        assert!(info.contains_key("db0"));
+        assert!(!info.keys().any(|k| k.starts_with("latency_percentiles_usec_") || k.starts_with("cmdstat_")));
+
+        let commands: Vec<CommandStat> = commandstats::parse_commandstats(&stats);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].calls, 10);
+        let latency: Vec<CommandLatency> = latency::parse_latencystats(&stats);
+        let names: Vec<&str> = latency.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["get", "set"]);
+        assert_eq!(latency[0].p999, Some(11.007));
+    }
+
+    #[test]
+    fn replicas_are_listed_in_index_order_across_gaps() {
+        let mut info: String = String::from("# Replication\r\nrole:master\r\nconnected_slaves:8\r\n");
+        for index in [0, 1, 2, 4, 5, 9, 10, 12] {
+            info += &format!("slave{}:ip=10.0.0.{},port=6379,state=online,offset={},lag=0\r\n", index, index, 1000 + index);
+        }
+        info += "slave_repl_offset:1012\r\nslave_read_only:1\r\nslave_priority:100\r\n";
+        info += "slave3:ip=10.0.0.3,port=6380,state=wait_bgsave,offset=0,lag=7\r\n";
+        let replicas: Vec<ReplicaLink> = get_replicas(&parse_redis_info(&info));
+
+        let ips: Vec<&str> = replicas.iter().map(|r| r.ip.as_str()).collect();
+        assert_eq!(ips, ["10.0.0.0", "10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4", "10.0.0.5", "10.0.0.9", "10.0.0.10", "10.0.0.12"]);
+        let syncing: &ReplicaLink = &replicas[3];
+        assert_eq!((syncing.port.as_str(), syncing.state.as_str(), syncing.offset, syncing.lag), ("6380", "wait_bgsave", 0, 7));
+        assert_eq!((replicas[8].state.as_str(), replicas[8].offset), ("online", 1012));
+    }
+
+
+    #[test]
+    fn keyspace_is_listed_in_database_order() {
+        let info: InfoMap = parse_redis_info("# Keyspace\r\n\
+            db10:keys=5,expires=0,avg_ttl=0\r\n\
+            db0:keys=1200,expires=300,avg_ttl=86400000,subexpiry=0\r\n\
+            db2:keys=7\r\n\
+            db3:expires=1\r\n\
+            dbsize:9\r\n");
+        let dbs: Vec<(u32, u64, u64, u64)> = get_keyspace(&info).iter()
+            .map(|d| (d.db, d.keys, d.expires, d.avg_ttl))
+            .collect();
+        assert_eq!(dbs, [(0, 1200, 300, 86400000), (2, 7, 0, 0), (10, 5, 0, 0)]);
+        assert!(get_keyspace(&parse_redis_info("# Keyspace\r\n")).is_empty());
+    }
+
+
+    #[test]
+    fn memory_stats_are_flattened_to_their_numeric_fields() {
+        let data = |s: &str| Value::Data(s.as_bytes().to_vec());
+        // Abridged `MEMORY STATS` of Redis 7.2
+        let reply = Value::Bulk(vec![
+            data("peak.allocated"), Value::Int(2_500_000),
+            data("total.allocated"), Value::Int(1_480_000),
+            data("db.0"), Value::Bulk(vec![data("overhead.hashtable.main"), Value::Int(72), data("overhead.hashtable.expires"), Value::Int(32)]),
+            data("keys.count"), Value::Int(1200),
+            data("dataset.percentage"), data("61.25"),
+            data("fragmentation"), data("2.4"),
+            data("allocator.frag"), Value::Nil,
+        ]);
+        let stats: BTreeMap<String, f64> = parse_memory_stats(&reply);
+        assert_eq!(stats.len(), 5);
+        assert_eq!(stats["peak.allocated"], 2_500_000.0);
+        assert_eq!(stats["keys.count"], 1200.0);
+        assert_eq!(stats["dataset.percentage"], 61.25);
+        assert_eq!(stats["fragmentation"], 2.4);
+        assert!(!stats.contains_key("db.0"));
+        assert!(parse_memory_stats(&Value::Nil).is_empty());
+    }
+
+}
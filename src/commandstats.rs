@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One `cmdstat_<name>` line of `INFO commandstats`.
+///
+/// The `_s` rates are filled in by the history against the previous poll
+/// and are `None` until a node has been polled twice in a row.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CommandStat {
+    pub(crate) name: String,
+    pub(crate) calls: u64,
+    pub(crate) usec: u64,
+    pub(crate) usec_per_call: f64,
+    pub(crate) rejected_calls: u64,
+    pub(crate) failed_calls: u64,
+    pub(crate) calls_s: Option<u64>,
+    pub(crate) rejected_s: Option<u64>,
+    pub(crate) failed_s: Option<u64>,
+}
+
+/// One `errorstat_<prefix>` line of `INFO errorstats`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ErrorStat {
+    /// Error prefix, e.g. `ERR` or `WRONGTYPE`.
+    pub(crate) name: String,
+    pub(crate) count: u64,
+    pub(crate) count_s: Option<u64>,
+}
+
+// `key=value,key=value` fields of a single stat line.
+fn fields(value: &str) -> HashMap<&str, &str> {
+    value.split(',').filter_map(|item| item.split_once('=')).collect()
+}
+
+fn num<T: std::str::FromStr + Default>(fields: &HashMap<&str, &str>, key: &str) -> T {
+    fields.get(key).and_then(|v| v.trim().parse().ok()).unwrap_or_default()
+}
+
+/// Parses the reply of `INFO commandstats`, busiest command first.
+pub(crate) fn parse_commandstats(reply: &str) -> Vec<CommandStat> {
+    let mut commands: Vec<CommandStat> = reply.lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim_end().split_once(':')?;
+            let name: &str = key.strip_prefix("cmdstat_")?;
+            let fields: HashMap<&str, &str> = fields(value);
+            Some(CommandStat {
+                name: name.to_string(),
+                calls: num(&fields, "calls"),
+                usec: num(&fields, "usec"),
+                usec_per_call: num(&fields, "usec_per_call"),
+                // Only reported since Redis 6.2
+                rejected_calls: num(&fields, "rejected_calls"),
+                failed_calls: num(&fields, "failed_calls"),
+                calls_s: None,
+                rejected_s: None,
+                failed_s: None,
+            })
+        })
+        .collect();
+    commands.sort_by(|a, b| b.calls.cmp(&a.calls).then_with(|| a.name.cmp(&b.name)));
+    commands
+}
+
+/// Parses the reply of `INFO errorstats`, empty before Redis 6.2.
+pub(crate) fn parse_errorstats(reply: &str) -> Vec<ErrorStat> {
+    reply.lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim_end().split_once(':')?;
+            let name: &str = key.strip_prefix("errorstat_")?;
+            Some(ErrorStat { name: name.to_string(), count: num(&fields(value), "count"), count_s: None })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMANDSTATS: &str = "# Commandstats\r\n\
+        cmdstat_set:calls=300,usec=900,usec_per_call=3.00,rejected_calls=2,failed_calls=1\r\n\
+        cmdstat_get:calls=500,usec=1000,usec_per_call=2.00,rejected_calls=0,failed_calls=0\r\n\
+        cmdstat_client|list:calls=300,usec=6000,usec_per_call=20.00,rejected_calls=0,failed_calls=0\r\n\
+        cmdstat_info:calls=12,usec=240,usec_per_call=20.00\r\n";
+
+    #[test]
+    fn commands_are_listed_busiest_first() {
+        let commands: Vec<CommandStat> = parse_commandstats(COMMANDSTATS);
+        let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["get", "client|list", "set", "info"]);
+        let set: &CommandStat = &commands[2];
+        assert_eq!((set.calls, set.usec, set.usec_per_call), (300, 900, 3.0));
+        assert_eq!((set.rejected_calls, set.failed_calls), (2, 1));
+        assert_eq!(set.calls_s, None);
+    }
+
+    #[test]
+    fn fields_missing_before_redis_6_2_are_zero() {
+        let info: &CommandStat = &parse_commandstats(COMMANDSTATS)[3];
+        assert_eq!((info.calls, info.rejected_calls, info.failed_calls), (12, 0, 0));
+        assert!(parse_commandstats("# Commandstats\r\n").is_empty());
+        assert!(parse_errorstats("").is_empty());
+    }
+
+    #[test]
+    fn errors_are_counted_per_prefix() {
+        let errors: Vec<ErrorStat> = parse_errorstats("# Errorstats\r\nerrorstat_ERR:count=12\r\nerrorstat_WRONGTYPE:count=3\r\n");
+        let counts: Vec<(&str, u64)> = errors.iter().map(|e| (e.name.as_str(), e.count)).collect();
+        assert_eq!(counts, [("ERR", 12), ("WRONGTYPE", 3)]);
+    }
+}
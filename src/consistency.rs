@@ -0,0 +1,225 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    cluster::{ClusterNode, SLOT_COUNT},
+    error::CollectError,
+};
+
+// Slot ranges listed per pair of disagreeing views before the rest is summarised.
+const MAX_SLOT_RANGES: usize = 5;
+
+/// One way in which the masters' views of the cluster differ.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Disagreement {
+    /// `unreachable`, `membership`, `role`, `failure`, `epoch` or `slots`
+    pub(crate) kind: &'static str,
+    pub(crate) message: String,
+}
+
+/// Result of comparing the `CLUSTER NODES` output of every master.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Consistency {
+    /// `ip:port` of every node whose view was compared, the seed first.
+    pub(crate) views: Vec<String>,
+    pub(crate) consistent: bool,
+    pub(crate) disagreements: Vec<Disagreement>,
+}
+
+// Display names for node IDs, taken from whichever view knows an address.
+fn node_names(views: &[(String, Vec<ClusterNode>)]) -> HashMap<String, String> {
+    let mut names: HashMap<String, String> = HashMap::new();
+    for (_, table) in views {
+        for node in table {
+            if let Some(instance) = node.addr.instance() {
+                names.entry(node.view.id.clone())
+                    .or_insert_with(|| format!("{}:{}", instance.ip, instance.port));
+            }
+        }
+    }
+    names
+}
+
+fn slot_owners(table: &[ClusterNode]) -> Vec<Option<&str>> {
+    let mut owners: Vec<Option<&str>> = vec![None; SLOT_COUNT];
+    for node in table.iter().filter(|n| n.is_master()) {
+        for &(start, end) in &node.slots {
+            owners[start as usize..=end as usize].fill(Some(node.view.id.as_str()));
+        }
+    }
+    owners
+}
+
+/// Compares the views of the cluster reported by its masters.
+///
+/// `views[0]` is the seed's view and serves as the reference for slot
+/// ownership; membership, roles, failure flags and config epochs are
+/// compared across all views.
+pub(crate) fn compare(views: &[(String, Vec<ClusterNode>)], unreachable: &[(String, CollectError)]) -> Consistency {
+    let names: HashMap<String, String> = node_names(views);
+    let name = |id: &str| names.get(id).cloned().unwrap_or_else(|| id.chars().take(8).collect());
+    let mut disagreements: Vec<Disagreement> = Vec::new();
+    let mut push = |kind: &'static str, message: String| disagreements.push(Disagreement { kind, message });
+
+    for (addr, e) in unreachable {
+        push("unreachable", format!("could not read the view of {}: {}", addr, e));
+    }
+
+    let all_ids: BTreeSet<&str> = views.iter()
+        .flat_map(|(_, table)| table.iter().map(|n| n.view.id.as_str()))
+        .collect();
+    for (viewer, table) in views {
+        let known: BTreeSet<&str> = table.iter().map(|n| n.view.id.as_str()).collect();
+        for id in all_ids.difference(&known) {
+            push("membership", format!("{} does not know node {}", viewer, name(id)));
+        }
+    }
+
+    // A failure flag every view agrees on is the cluster's verdict, not a
+    // disagreement. A node's own line never carries one, so it is left out.
+    let mut listings: BTreeMap<&str, Vec<(&str, &ClusterNode)>> = BTreeMap::new();
+    for (viewer, table) in views {
+        for node in table.iter().filter(|n| !n.has_flag("myself")) {
+            listings.entry(&node.view.id).or_default().push((viewer, node));
+        }
+    }
+    for (id, listed) in &listings {
+        for flag in ["fail", "fail?"] {
+            let flagging: Vec<&str> = listed.iter().filter(|(_, n)| n.has_flag(flag)).map(|(v, _)| *v).collect();
+            let others: Vec<&str> = listed.iter().filter(|(_, n)| !n.has_flag(flag)).map(|(v, _)| *v).collect();
+            if !flagging.is_empty() && !others.is_empty() {
+                push("failure", format!(
+                    "{} is flagged as {} by {} but not by {}", name(id), flag, flagging.join(", "), others.join(", "),
+                ));
+            }
+        }
+    }
+
+    // Role and config epoch per node, as seen by each view.
+    let mut roles: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    let mut epochs: BTreeMap<&str, BTreeMap<u64, Vec<&str>>> = BTreeMap::new();
+    for (viewer, table) in views {
+        for node in table {
+            let role: &str = if node.is_master() { "master" } else if node.is_replica() { "replica" } else { "unknown" };
+            roles.entry(&node.view.id).or_default().entry(role).or_default().push(viewer);
+            epochs.entry(&node.view.id).or_default().entry(node.config_epoch).or_default().push(viewer);
+        }
+    }
+    for (id, seen) in roles.iter().filter(|(_, seen)| seen.len() > 1) {
+        let detail: Vec<String> = seen.iter().map(|(role, viewers)| format!("{} on {}", role, viewers.join(", "))).collect();
+        push("role", format!("role of {}: {}", name(id), detail.join("; ")));
+    }
+    for (id, seen) in epochs.iter().filter(|(_, seen)| seen.len() > 1) {
+        let detail: Vec<String> = seen.iter().map(|(epoch, viewers)| format!("{} on {}", epoch, viewers.join(", "))).collect();
+        push("epoch", format!("config epoch of {}: {}", name(id), detail.join("; ")));
+    }
+
+    if let Some(((reference, base), rest)) = views.split_first() {
+        let base_owners: Vec<Option<&str>> = slot_owners(base);
+        for (viewer, table) in rest {
+            let owners: Vec<Option<&str>> = slot_owners(table);
+            // Runs of consecutive slots with the same pair of owners.
+            let mut runs: Vec<(usize, usize, Option<&str>, Option<&str>)> = Vec::new();
+            for slot in (0..SLOT_COUNT).filter(|s| owners[*s] != base_owners[*s]) {
+                match runs.last_mut() {
+                    Some(run) if run.1 + 1 == slot && run.2 == owners[slot] && run.3 == base_owners[slot] => run.1 = slot,
+                    _ => runs.push((slot, slot, owners[slot], base_owners[slot])),
+                }
+            }
+            let owner = |id: Option<&str>| id.map(&name).unwrap_or_else(|| "nobody".to_string());
+            for (start, end, theirs, ours) in runs.iter().take(MAX_SLOT_RANGES) {
+                let slots: String = if start == end { format!("slot {}", start) } else { format!("slots {}-{}", start, end) };
+                push("slots", format!(
+                    "{} assigns {} to {}, {} to {}", viewer, slots, owner(*theirs), reference, owner(*ours),
+                ));
+            }
+            if runs.len() > MAX_SLOT_RANGES {
+                push("slots", format!(
+                    "{} disagrees with {} on {} more slot ranges", viewer, reference, runs.len() - MAX_SLOT_RANGES,
+                ));
+            }
+        }
+    }
+
+    Consistency {
+        views: views.iter().map(|(addr, _)| addr.clone()).collect(),
+        consistent: disagreements.is_empty(),
+        disagreements,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::parse_cluster_nodes;
+
+    const A: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const B: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const C: &str = "cccccccccccccccccccccccccccccccccccccccc";
+
+    // The view of the master on `port` of three masters splitting the slots,
+    // with `c_flags` on C's line.
+    fn view(port: u16, c_flags: &str) -> (String, Vec<ClusterNode>) {
+        let flags = |p: u16, own: &str| if p == port { format!("myself,{}", own) } else { own.to_string() };
+        let text: String = format!(
+            "{A} 127.0.0.1:7001@17001 {} - 0 0 1 connected 0-5460\n\
+             {B} 127.0.0.1:7002@17002 {} - 0 0 2 connected 5461-10922\n\
+             {C} 127.0.0.1:7003@17003 {} - 0 0 3 connected 10923-16383\n",
+            flags(7001, "master"), flags(7002, "master"), flags(7003, c_flags),
+        );
+        (format!("127.0.0.1:{}", port), parse_cluster_nodes(&text))
+    }
+
+    fn kinds(result: &Consistency) -> Vec<&str> {
+        result.disagreements.iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn identical_views_are_consistent() {
+        let result: Consistency = compare(&[view(7001, "master"), view(7002, "master"), view(7003, "master")], &[]);
+        assert!(result.consistent, "{:?}", result.disagreements);
+        assert_eq!(result.views, ["127.0.0.1:7001", "127.0.0.1:7002", "127.0.0.1:7003"]);
+    }
+
+    #[test]
+    fn an_agreed_failure_is_not_a_disagreement() {
+        let result: Consistency = compare(&[view(7001, "master,fail"), view(7002, "master,fail")], &[]);
+        assert!(result.consistent, "{:?}", result.disagreements);
+    }
+
+    #[test]
+    fn a_failure_flag_only_some_views_set_is_reported() {
+        // C's own line never carries the flag, so its view does not count against it
+        let result: Consistency = compare(&[view(7001, "master,fail?"), view(7002, "master,fail?"), view(7003, "master")], &[]);
+        assert!(result.consistent, "{:?}", result.disagreements);
+
+        let result: Consistency = compare(&[view(7001, "master,fail?"), view(7002, "master")], &[]);
+        assert_eq!(kinds(&result), ["failure"]);
+        assert_eq!(
+            result.disagreements[0].message,
+            "127.0.0.1:7003 is flagged as fail? by 127.0.0.1:7001 but not by 127.0.0.1:7002",
+        );
+
+        let result: Consistency = compare(&[view(7001, "master,fail"), view(7002, "master,fail?")], &[]);
+        assert_eq!(kinds(&result), ["failure", "failure"]);
+    }
+
+    #[test]
+    fn roles_epochs_slots_and_membership() {
+        let (seed, table) = view(7001, "master");
+        let mut other: (String, Vec<ClusterNode>) = view(7002, "slave");
+        other.1[2].view.master_id = Some(A.to_string());
+        other.1[2].slots.clear();
+        other.1[1].config_epoch = 4;
+        let result: Consistency = compare(&[(seed.clone(), table.clone()), other], &[]);
+        assert_eq!(kinds(&result), ["role", "epoch", "slots"]);
+        assert_eq!(result.disagreements[2].message, "127.0.0.1:7002 assigns slots 10923-16383 to nobody, 127.0.0.1:7001 to 127.0.0.1:7003");
+
+        let (viewer, mut partial) = view(7002, "master");
+        partial.remove(2);
+        partial[0].slots.push((10923, 16383));
+        let result: Consistency = compare(&[(seed, table), (viewer, partial)], &[("127.0.0.1:7003".to_string(), CollectError::Timeout("no reply".to_string()))]);
+        assert_eq!(kinds(&result), ["unreachable", "membership", "slots"]);
+        assert_eq!(result.disagreements[1].message, "127.0.0.1:7002 does not know node 127.0.0.1:7003");
+    }
+}
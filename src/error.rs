@@ -0,0 +1,85 @@
+use redis::{ErrorKind, RedisError};
+use serde::Serialize;
+use std::fmt;
+
+/// Why a node could not be collected on this tick.
+#[derive(Debug, Clone)]
+pub(crate) enum CollectError {
+    ConnectionRefused(String),
+    AuthFailed(String),
+    Timeout(String),
+    Loading,
+    Parse(String),
+    UnsupportedRole(String),
+    /// The cluster lists the node but it cannot be polled, e.g. `noaddr` or `handshake`.
+    ClusterState(String),
+    Io(String),
+    Redis(String),
+}
+
+impl CollectError {
+    /// Stable identifier used by the dashboard to style the error.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            CollectError::ConnectionRefused(_) => "connection_refused",
+            CollectError::AuthFailed(_) => "auth_failed",
+            CollectError::Timeout(_) => "timeout",
+            CollectError::Loading => "loading",
+            CollectError::Parse(_) => "parse_error",
+            CollectError::UnsupportedRole(_) => "unsupported_role",
+            CollectError::ClusterState(_) => "cluster_state",
+            CollectError::Io(_) => "io_error",
+            CollectError::Redis(_) => "redis_error",
+        }
+    }
+}
+
+impl fmt::Display for CollectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollectError::ConnectionRefused(e) => write!(f, "{}", e),
+            CollectError::AuthFailed(e) => write!(f, "authentication failed: {}", e),
+            CollectError::Timeout(e) => write!(f, "timed out: {}", e),
+            CollectError::Loading => write!(f, "Redis is loading the dataset in memory"),
+            CollectError::Parse(e) => write!(f, "failed to parse reply: {}", e),
+            CollectError::UnsupportedRole(role) => write!(f, "unsupported role '{}'", role),
+            CollectError::ClusterState(e) => write!(f, "{}", e),
+            CollectError::Io(e) => write!(f, "I/O error: {}", e),
+            CollectError::Redis(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CollectError {}
+
+impl From<RedisError> for CollectError {
+    fn from(e: RedisError) -> Self {
+        let message: String = e.to_string();
+        if e.is_timeout() {
+            return CollectError::Timeout(message);
+        }
+        if e.is_connection_refusal() {
+            return CollectError::ConnectionRefused(message);
+        }
+        match (e.kind(), e.code()) {
+            (ErrorKind::AuthenticationFailed, _) | (_, Some("NOAUTH" | "WRONGPASS")) => CollectError::AuthFailed(message),
+            (ErrorKind::BusyLoadingError, _) => CollectError::Loading,
+            (ErrorKind::TypeError | ErrorKind::ParseError, _) => CollectError::Parse(message),
+            (ErrorKind::IoError, _) => CollectError::Io(message),
+            _ => CollectError::Redis(message),
+        }
+    }
+}
+
+/// Serialized form of a `CollectError` in the SSE payload.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ErrorReport {
+    kind: &'static str,
+    message: String,
+}
+
+impl From<&CollectError> for ErrorReport {
+    fn from(e: &CollectError) -> Self {
+        ErrorReport { kind: e.kind(), message: e.to_string() }
+    }
+}
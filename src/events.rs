@@ -0,0 +1,282 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::{
+    collector::NodeReport,
+    history::info_num,
+    nodelog::{Logged, NodeLog},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EventKind {
+    RoleChange,
+    Restart,
+    UptimeReset,
+    ReplicaAdded,
+    ReplicaRemoved,
+    MasterChange,
+    LinkDown,
+    FullSync,
+    BgsaveStarted,
+    BgsaveFinished,
+    AofRewriteStarted,
+    AofRewriteFinished,
+    PersistenceFailed,
+}
+
+/// Something that changed on a node between two ticks.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Event {
+    pub(crate) ts: u64,
+    /// `ip:port` of the node the event happened on.
+    pub(crate) node: String,
+    pub(crate) kind: EventKind,
+    pub(crate) message: String,
+}
+
+// What is compared between two successful polls of a node.
+struct NodeState {
+    role: String,
+    run_id: String,
+    process_id: String,
+    uptime: u64,
+    replicas: BTreeSet<String>,
+    master: Option<String>,
+    link_status: Option<String>,
+    sync_in_progress: bool,
+    sync_full: u64,
+    bgsave_in_progress: bool,
+    last_save_time: u64,
+    bgsave_status: String,
+    aof_rewrite_in_progress: bool,
+    aof_rewrite_status: String,
+}
+
+impl NodeState {
+    fn of(report: &NodeReport) -> Self {
+        let info = &report.info;
+        let get = |k: &str| info.get(k).cloned().unwrap_or_default();
+        NodeState {
+            role: report.role.clone(),
+            run_id: get("run_id"),
+            process_id: get("process_id"),
+            uptime: info_num(info, "uptime_in_seconds"),
+            replicas: report.replicas.iter().map(|r| format!("{}:{}", r.ip, r.port)).collect(),
+            master: info.get("master_host")
+                .zip(info.get("master_port"))
+                .map(|(host, port)| format!("{}:{}", host, port)),
+            link_status: info.get("master_link_status").cloned(),
+            sync_in_progress: info_num::<u8>(info, "master_sync_in_progress") == 1,
+            sync_full: info_num(info, "sync_full"),
+            bgsave_in_progress: info_num::<u8>(info, "rdb_bgsave_in_progress") == 1,
+            last_save_time: info_num(info, "rdb_last_save_time"),
+            bgsave_status: get("rdb_last_bgsave_status"),
+            aof_rewrite_in_progress: info_num::<u8>(info, "aof_rewrite_in_progress") == 1,
+            aof_rewrite_status: get("aof_last_bgrewrite_status"),
+        }
+    }
+}
+
+impl Logged for Event {
+    fn ts(&self) -> u64 {
+        self.ts
+    }
+}
+
+/// Bounded log of the events detected for one cluster.
+#[derive(Default)]
+pub(crate) struct EventLog {
+    // The last state of every node, `None` until it was reachable once
+    log: NodeLog<Option<NodeState>, Event>,
+}
+
+fn diff(prev: &NodeState, curr: &NodeState) -> Vec<(EventKind, String)> {
+    let mut found: Vec<(EventKind, String)> = Vec::new();
+    if prev.role != curr.role {
+        found.push((EventKind::RoleChange, format!("role changed from {} to {}", prev.role, curr.role)));
+    }
+    if prev.run_id != curr.run_id || prev.process_id != curr.process_id {
+        found.push((EventKind::Restart, format!(
+            "restarted (process_id {} -> {}, run_id {} -> {})",
+            prev.process_id, curr.process_id, prev.run_id, curr.run_id,
+        )));
+    } else if curr.uptime < prev.uptime {
+        found.push((EventKind::UptimeReset, format!("uptime went back from {}s to {}s", prev.uptime, curr.uptime)));
+    }
+    for added in curr.replicas.difference(&prev.replicas) {
+        found.push((EventKind::ReplicaAdded, format!("replica {} attached", added)));
+    }
+    for removed in prev.replicas.difference(&curr.replicas) {
+        found.push((EventKind::ReplicaRemoved, format!("replica {} detached", removed)));
+    }
+    if let (Some(before), Some(after)) = (&prev.master, &curr.master)
+        && before != after {
+        found.push((EventKind::MasterChange, format!("now replicating from {} instead of {}", after, before)));
+    }
+    if curr.link_status.as_deref() == Some("down") && prev.link_status.as_deref() != Some("down") {
+        found.push((EventKind::LinkDown, "master_link_status is down".to_string()));
+    }
+    // Seen from both ends: the replica loading the payload and the master counting it.
+    if curr.sync_in_progress && !prev.sync_in_progress {
+        found.push((EventKind::FullSync, "full sync from the master started".to_string()));
+    }
+    if curr.sync_full > prev.sync_full {
+        found.push((EventKind::FullSync, format!("served {} full sync(s) to replicas", curr.sync_full - prev.sync_full)));
+    }
+    // A save that started and finished between two polls only moves the save time.
+    if curr.bgsave_in_progress && !prev.bgsave_in_progress {
+        found.push((EventKind::BgsaveStarted, "BGSAVE started".to_string()));
+    } else if prev.bgsave_in_progress && !curr.bgsave_in_progress {
+        found.push((EventKind::BgsaveFinished, format!("BGSAVE finished ({})", curr.bgsave_status)));
+    } else if curr.last_save_time > prev.last_save_time && prev.last_save_time > 0 {
+        found.push((EventKind::BgsaveFinished, "RDB saved".to_string()));
+    }
+    if curr.aof_rewrite_in_progress && !prev.aof_rewrite_in_progress {
+        found.push((EventKind::AofRewriteStarted, "AOF rewrite started".to_string()));
+    } else if prev.aof_rewrite_in_progress && !curr.aof_rewrite_in_progress {
+        found.push((EventKind::AofRewriteFinished, format!("AOF rewrite finished ({})", curr.aof_rewrite_status)));
+    }
+    if curr.bgsave_status == "err" && prev.bgsave_status != "err" {
+        found.push((EventKind::PersistenceFailed, "BGSAVE failed".to_string()));
+    }
+    if curr.aof_rewrite_status == "err" && prev.aof_rewrite_status != "err" {
+        found.push((EventKind::PersistenceFailed, "AOF rewrite failed".to_string()));
+    }
+    found
+}
+
+impl EventLog {
+    /// Compares every reachable node against its previous poll and logs what changed.
+    ///
+    /// Unreachable nodes keep their last state, so a crash-restart that
+    /// happened while a node was down is reported when it comes back.
+    pub(crate) fn observe(&self, reports: &[NodeReport], ts: u64) -> Vec<Event> {
+        self.log.observe(reports, |report, prev| {
+            let curr: NodeState = NodeState::of(report);
+            let found: Vec<(EventKind, String)> = prev.as_ref().map(|prev| diff(prev, &curr)).unwrap_or_default();
+            *prev = Some(curr);
+            found.into_iter()
+                .map(|(kind, message)| Event { ts, node: report.addr(), kind, message })
+                .collect()
+        })
+    }
+
+    /// Logged events newer than `since` (unix milliseconds), oldest first.
+    pub(crate) fn since(&self, since: u64) -> Vec<Event> {
+        self.log.since(since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::{parse_redis_info, NodeProbe}, error::CollectError, RedisInstance};
+
+    const MASTER: &str = "role:master\r\nrun_id:aaa\r\nprocess_id:10\r\nuptime_in_seconds:500\r\n\
+        rdb_bgsave_in_progress:0\r\nrdb_last_save_time:1000\r\nrdb_last_bgsave_status:ok\r\n\
+        aof_rewrite_in_progress:0\r\naof_last_bgrewrite_status:ok\r\nsync_full:1\r\n\
+        slave0:ip=10.0.0.2,port=6379,state=online,offset=100,lag=0\r\n";
+
+    // INFO of the master with some fields replaced or added.
+    fn info(changes: &[(&str, &str)]) -> String {
+        let mut lines: Vec<String> = MASTER.lines().map(String::from).collect();
+        for (key, value) in changes {
+            lines.retain(|line| !line.starts_with(&format!("{}:", key)));
+            if !value.is_empty() {
+                lines.push(format!("{}:{}", key, value));
+            }
+        }
+        lines.join("\r\n")
+    }
+
+    fn up(info: &str) -> NodeReport {
+        let instance = RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() };
+        let probe = NodeProbe { info: parse_redis_info(info), ..Default::default() };
+        NodeReport::new(&instance, "master", None, Ok(probe))
+    }
+
+    fn down() -> NodeReport {
+        let instance = RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() };
+        NodeReport::new(&instance, "master", None, Err(CollectError::Timeout("no reply within 1000 ms".to_string())))
+    }
+
+    // Kinds of the events found between two polls of the node.
+    fn kinds(before: &str, after: &str) -> Vec<EventKind> {
+        let log = EventLog::default();
+        assert!(log.observe(&[up(before)], 1000).is_empty());
+        log.observe(&[up(after)], 2000).into_iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn an_unchanged_node_has_no_events() {
+        assert!(kinds(MASTER, &info(&[("uptime_in_seconds", "501")])).is_empty());
+    }
+
+    #[test]
+    fn a_promoted_replica_changes_role_and_master() {
+        let replica: String = info(&[("role", "slave"), ("master_host", "10.0.0.9"), ("master_port", "6379"), ("slave0", "")]);
+        assert_eq!(kinds(&replica, &info(&[("role", "slave"), ("master_host", "10.0.0.8"), ("master_port", "6379"), ("slave0", "")])), [EventKind::MasterChange]);
+        assert_eq!(kinds(&replica, &info(&[("slave0", "")])), [EventKind::RoleChange]);
+    }
+
+    #[test]
+    fn restarts_are_told_apart_from_uptime_resets() {
+        assert_eq!(kinds(MASTER, &info(&[("run_id", "bbb"), ("uptime_in_seconds", "3")])), [EventKind::Restart]);
+        assert_eq!(kinds(MASTER, &info(&[("process_id", "11")])), [EventKind::Restart]);
+        assert_eq!(kinds(MASTER, &info(&[("uptime_in_seconds", "3")])), [EventKind::UptimeReset]);
+    }
+
+    #[test]
+    fn replicas_attaching_and_detaching() {
+        let two: String = info(&[("slave1", "ip=10.0.0.3,port=6379,state=online,offset=100,lag=0")]);
+        assert_eq!(kinds(MASTER, &two), [EventKind::ReplicaAdded]);
+        let events: Vec<Event> = {
+            let log = EventLog::default();
+            log.observe(&[up(&two)], 1000);
+            log.observe(&[up(&info(&[("slave0", "")]))], 2000)
+        };
+        let messages: Vec<&str> = events.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["replica 10.0.0.2:6379 detached", "replica 10.0.0.3:6379 detached"]);
+    }
+
+    #[test]
+    fn a_replica_link_going_down_and_a_full_sync() {
+        let replica: String = info(&[("role", "slave"), ("master_host", "10.0.0.9"), ("master_port", "6379"), ("master_link_status", "up"), ("slave0", "")]);
+        let lost: String = info(&[("role", "slave"), ("master_host", "10.0.0.9"), ("master_port", "6379"), ("master_link_status", "down"), ("master_sync_in_progress", "1"), ("slave0", "")]);
+        assert_eq!(kinds(&replica, &lost), [EventKind::LinkDown, EventKind::FullSync]);
+        assert!(kinds(&lost, &lost).is_empty());
+        assert_eq!(kinds(MASTER, &info(&[("sync_full", "3")])), [EventKind::FullSync]);
+    }
+
+    #[test]
+    fn bgsave_and_aof_rewrite_transitions() {
+        let saving: String = info(&[("rdb_bgsave_in_progress", "1")]);
+        assert_eq!(kinds(MASTER, &saving), [EventKind::BgsaveStarted]);
+        assert_eq!(kinds(&saving, &info(&[("rdb_last_save_time", "1010")])), [EventKind::BgsaveFinished]);
+        // Started and finished between two polls
+        assert_eq!(kinds(MASTER, &info(&[("rdb_last_save_time", "1010")])), [EventKind::BgsaveFinished]);
+        assert_eq!(
+            kinds(&saving, &info(&[("rdb_last_bgsave_status", "err")])),
+            [EventKind::BgsaveFinished, EventKind::PersistenceFailed],
+        );
+
+        let rewriting: String = info(&[("aof_rewrite_in_progress", "1")]);
+        assert_eq!(kinds(MASTER, &rewriting), [EventKind::AofRewriteStarted]);
+        assert_eq!(
+            kinds(&rewriting, &info(&[("aof_last_bgrewrite_status", "err")])),
+            [EventKind::AofRewriteFinished, EventKind::PersistenceFailed],
+        );
+    }
+
+    #[test]
+    fn a_restart_while_unreachable_is_reported_when_the_node_is_back() {
+        let log = EventLog::default();
+        log.observe(&[up(MASTER)], 1000);
+        assert!(log.observe(&[down()], 2000).is_empty());
+        let events: Vec<Event> = log.observe(&[up(&info(&[("run_id", "bbb"), ("uptime_in_seconds", "3")]))], 3000);
+        assert_eq!(events.iter().map(|e| e.kind).collect::<Vec<_>>(), [EventKind::Restart]);
+        assert_eq!(log.since(2000).len(), 1);
+        assert!(log.since(3000).is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::collector::{NodeReport, NodeStatus};
+
+// One hour of samples at the one-second poll interval.
+const HISTORY_LEN: usize = 3600;
+const HISTORY_WINDOW_MS: u64 = 3600 * 1000;
+
+/// Derived metrics for one node at one tick.
+///
+/// Rates are per second against the previous sample and are `None` for the
+/// first sample after startup or after the node was unreachable.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Sample {
+    pub(crate) ts: u64,
+    pub(crate) cpu_usr: f64,
+    pub(crate) cpu_sys: f64,
+    pub(crate) clients: u64,
+    pub(crate) mem: u64,
+    pub(crate) mem_rss: u64,
+    /// `used_memory / maxmemory`, absent without a `maxmemory` limit.
+    pub(crate) mem_util: Option<f64>,
+    /// `used_memory_rss / used_memory` as reported by INFO.
+    pub(crate) frag_ratio: Option<f64>,
+    /// Key count per database number.
+    pub(crate) keys: BTreeMap<u32, u64>,
+    /// Bytes a replica is behind its master.
+    pub(crate) repl_lag: Option<u64>,
+    /// `master_last_io_seconds_ago` of a replica with its link up.
+    pub(crate) repl_io: Option<u64>,
+    /// `repl_backlog_histlen` while the backlog is active: a replica further
+    /// behind than this can only come back with a full sync.
+    pub(crate) repl_backlog: Option<u64>,
+    /// Full and partial resynchronizations served to replicas since startup.
+    pub(crate) sync_full: u64,
+    pub(crate) sync_partial_ok: u64,
+    pub(crate) sync_partial_err: u64,
+    pub(crate) cmd_s: Option<u64>,
+    pub(crate) rej_s: Option<u64>,
+    pub(crate) exp_s: Option<u64>,
+    pub(crate) evt_s: Option<u64>,
+    pub(crate) hit_s: Option<u64>,
+    pub(crate) mis_s: Option<u64>,
+}
+
+// Raw INFO counters the rates are derived from.
+#[derive(Debug, Clone, Copy)]
+struct Counters {
+    ts: u64,
+    total_commands_processed: u64,
+    rejected_connections: u64,
+    expired_keys: u64,
+    evicted_keys: u64,
+    keyspace_hits: u64,
+    keyspace_misses: u64,
+}
+
+#[derive(Default)]
+struct NodeHistory {
+    samples: VecDeque<Sample>,
+    prev: Option<Counters>,
+    // calls, rejected_calls, failed_calls per command at `prev`
+    prev_commands: HashMap<String, (u64, u64, u64)>,
+    prev_errors: HashMap<String, u64>,
+}
+
+/// Per-node ring buffers of samples for one cluster, keyed by `ip:port`.
+#[derive(Default)]
+pub(crate) struct History {
+    nodes: Mutex<HashMap<String, NodeHistory>>,
+}
+
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+pub(crate) fn info_num<T: std::str::FromStr + Default>(info: &HashMap<String, String>, key: &str) -> T {
+    info.get(key).and_then(|v| v.trim().parse().ok()).unwrap_or_default()
+}
+
+fn rate(curr: u64, prev: u64, secs: f64) -> u64 {
+    // Counters go backwards when a node restarts; treat that as no activity.
+    (curr.saturating_sub(prev) as f64 / secs).round() as u64
+}
+
+impl History {
+    /// Records a sample for every reachable node of a snapshot and attaches it to the report.
+    pub(crate) fn observe(&self, reports: &mut [NodeReport], ts: u64) {
+        let mut nodes = self.nodes.lock().unwrap();
+        for report in reports.iter_mut() {
+            let node: &mut NodeHistory = nodes.entry(report.addr()).or_default();
+            if report.status != NodeStatus::Up {
+                node.prev = None;
+                continue;
+            }
+            let sample: Sample = node.record(report, ts);
+            report.sample = Some(sample);
+        }
+        // Forget nodes that have not produced a sample for a whole window.
+        nodes.retain(|_, node| {
+            node.samples.back().is_some_and(|s| ts.saturating_sub(s.ts) < HISTORY_WINDOW_MS)
+                || node.prev.is_some()
+        });
+    }
+
+    /// Samples newer than `since` (unix milliseconds) for every known node.
+    pub(crate) fn since(&self, since: u64) -> HashMap<String, Vec<Sample>> {
+        self.nodes.lock().unwrap().iter()
+            .map(|(addr, node)| {
+                let samples: Vec<Sample> = node.samples.iter()
+                    .filter(|s| s.ts > since)
+                    .cloned()
+                    .collect();
+                (addr.clone(), samples)
+            })
+            .collect()
+    }
+}
+
+impl NodeHistory {
+    fn record(&mut self, report: &mut NodeReport, ts: u64) -> Sample {
+        let info: &HashMap<String, String> = &report.info;
+        let counters = Counters {
+            ts,
+            total_commands_processed: info_num(info, "total_commands_processed"),
+            rejected_connections: info_num(info, "rejected_connections"),
+            expired_keys: info_num(info, "expired_keys"),
+            evicted_keys: info_num(info, "evicted_keys"),
+            keyspace_hits: info_num(info, "keyspace_hits"),
+            keyspace_misses: info_num(info, "keyspace_misses"),
+        };
+        let mut sample = Sample {
+            ts,
+            cpu_usr: info_num(info, "used_cpu_user"),
+            cpu_sys: info_num(info, "used_cpu_sys"),
+            clients: info_num(info, "connected_clients"),
+            mem: info_num(info, "used_memory"),
+            mem_rss: info_num(info, "used_memory_rss"),
+            mem_util: Some(info_num::<f64>(info, "maxmemory"))
+                .filter(|max| *max > 0.0)
+                .map(|max| info_num::<f64>(info, "used_memory") / max),
+            frag_ratio: info.get("mem_fragmentation_ratio").and_then(|v| v.trim().parse().ok()),
+            keys: report.keyspace.iter().map(|d| (d.db, d.keys)).collect(),
+            repl_lag: report.replication.as_ref().and_then(|r| r.lag_bytes),
+            repl_io: report.replication.as_ref().and_then(|r| r.last_io_secs),
+            repl_backlog: Some(info_num(info, "repl_backlog_histlen"))
+                .filter(|_| info_num::<u8>(info, "repl_backlog_active") == 1),
+            sync_full: info_num(info, "sync_full"),
+            sync_partial_ok: info_num(info, "sync_partial_ok"),
+            sync_partial_err: info_num(info, "sync_partial_err"),
+            cmd_s: None,
+            rej_s: None,
+            exp_s: None,
+            evt_s: None,
+            hit_s: None,
+            mis_s: None,
+        };
+        if let Some(prev) = self.prev.filter(|p| p.ts < ts) {
+            let secs: f64 = (ts - prev.ts) as f64 / 1000.0;
+            sample.cmd_s = Some(rate(counters.total_commands_processed, prev.total_commands_processed, secs));
+            sample.rej_s = Some(rate(counters.rejected_connections, prev.rejected_connections, secs));
+            sample.exp_s = Some(rate(counters.expired_keys, prev.expired_keys, secs));
+            sample.evt_s = Some(rate(counters.evicted_keys, prev.evicted_keys, secs));
+            sample.hit_s = Some(rate(counters.keyspace_hits, prev.keyspace_hits, secs));
+            sample.mis_s = Some(rate(counters.keyspace_misses, prev.keyspace_misses, secs));
+            // Commands and errors are only listed once they happened, so a new one starts from zero.
+            for cmd in report.commands.iter_mut() {
+                let (calls, rejected, failed) = self.prev_commands.get(&cmd.name).copied().unwrap_or_default();
+                cmd.calls_s = Some(rate(cmd.calls, calls, secs));
+                cmd.rejected_s = Some(rate(cmd.rejected_calls, rejected, secs));
+                cmd.failed_s = Some(rate(cmd.failed_calls, failed, secs));
+            }
+            for err in report.errors.iter_mut() {
+                err.count_s = Some(rate(err.count, self.prev_errors.get(&err.name).copied().unwrap_or_default(), secs));
+            }
+        }
+        self.prev = Some(counters);
+        self.prev_commands = report.commands.iter()
+            .map(|c| (c.name.clone(), (c.calls, c.rejected_calls, c.failed_calls)))
+            .collect();
+        self.prev_errors = report.errors.iter().map(|e| (e.name.clone(), e.count)).collect();
+        self.samples.push_back(sample.clone());
+        if self.samples.len() > HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::NodeProbe, commandstats::parse_commandstats, error::CollectError, RedisInstance};
+
+    fn instance() -> RedisInstance {
+        RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() }
+    }
+
+    fn up(commands: u64) -> NodeReport {
+        let info: HashMap<String, String> = [
+            ("total_commands_processed".to_string(), commands.to_string()),
+            ("used_memory".to_string(), "1000".to_string()),
+            ("maxmemory".to_string(), "4000".to_string()),
+        ].into();
+        let probe = NodeProbe {
+            info,
+            commands: parse_commandstats(&format!("cmdstat_get:calls={},usec=0,usec_per_call=0.00", commands)),
+            ..Default::default()
+        };
+        NodeReport::new(&instance(), "master", None, Ok(probe))
+    }
+
+    fn down() -> NodeReport {
+        NodeReport::new(&instance(), "master", None, Err(CollectError::Timeout("no reply within 1000 ms".to_string())))
+    }
+
+    fn observe(history: &History, report: NodeReport, ts: u64) -> NodeReport {
+        let mut reports: Vec<NodeReport> = vec![report];
+        history.observe(&mut reports, ts);
+        reports.remove(0)
+    }
+
+    #[test]
+    fn rates_are_per_second_against_the_previous_sample() {
+        let history = History::default();
+        let first: Sample = observe(&history, up(100), 1000).sample.unwrap();
+        assert_eq!(first.cmd_s, None);
+        assert_eq!(first.mem_util, Some(0.25));
+
+        let second: NodeReport = observe(&history, up(400), 3000);
+        assert_eq!(second.sample.unwrap().cmd_s, Some(150));
+        assert_eq!(second.commands[0].calls_s, Some(150));
+    }
+
+    #[test]
+    fn a_counter_reset_counts_as_no_activity() {
+        let history = History::default();
+        observe(&history, up(5000), 1000);
+        let restarted: NodeReport = observe(&history, up(20), 2000);
+        assert_eq!(restarted.sample.unwrap().cmd_s, Some(0));
+        assert_eq!(restarted.commands[0].calls_s, Some(0));
+        assert_eq!(observe(&history, up(120), 3000).sample.unwrap().cmd_s, Some(100));
+    }
+
+    #[test]
+    fn no_rate_right_after_a_node_was_unreachable() {
+        let history = History::default();
+        observe(&history, up(100), 1000);
+        assert!(observe(&history, down(), 2000).sample.is_none());
+        // Spread over the gap, the counter would show a bogus rate
+        assert_eq!(observe(&history, up(10_000), 3000).sample.unwrap().cmd_s, None);
+        assert_eq!(observe(&history, up(10_100), 4000).sample.unwrap().cmd_s, Some(100));
+    }
+
+    #[test]
+    fn samples_are_filtered_by_time() {
+        let history = History::default();
+        for ts in [1000, 2000, 3000] {
+            observe(&history, up(ts), ts);
+        }
+        let since: HashMap<String, Vec<Sample>> = history.since(1000);
+        let times: Vec<u64> = since["10.0.0.1:6379"].iter().map(|s| s.ts).collect();
+        assert_eq!(times, [2000, 3000]);
+        assert!(history.since(3000)["10.0.0.1:6379"].is_empty());
+    }
+
+    #[test]
+    fn an_hour_of_samples_is_kept() {
+        let history = History::default();
+        for i in 0..=HISTORY_LEN as u64 {
+            observe(&history, up(i), 1000 * (i + 1));
+        }
+        let samples: Vec<Sample> = history.since(0).remove("10.0.0.1:6379").unwrap();
+        assert_eq!(samples.len(), HISTORY_LEN);
+        assert_eq!(samples[0].ts, 2000);
+        assert_eq!(samples.last().unwrap().ts, 1000 * (HISTORY_LEN as u64 + 1));
+    }
+
+    #[test]
+    fn nodes_without_a_sample_for_a_whole_window_are_forgotten() {
+        let history = History::default();
+        observe(&history, up(100), 1000);
+        observe(&history, down(), 2000);
+        assert!(history.since(0).contains_key("10.0.0.1:6379"));
+        history.observe(&mut [], 1000 + HISTORY_WINDOW_MS);
+        assert!(history.since(0).is_empty());
+    }
+
+    #[test]
+    fn the_backlog_is_only_sampled_while_active() {
+        let history = History::default();
+        let mut report: NodeReport = up(100);
+        report.info.insert("repl_backlog_histlen".to_string(), "65536".to_string());
+        report.info.insert("repl_backlog_active".to_string(), "1".to_string());
+        assert_eq!(observe(&history, report.clone(), 1000).sample.unwrap().repl_backlog, Some(65536));
+        report.info.insert("repl_backlog_active".to_string(), "0".to_string());
+        assert_eq!(observe(&history, report, 2000).sample.unwrap().repl_backlog, None);
+    }
+
+}
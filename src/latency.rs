@@ -0,0 +1,203 @@
+use redis::Value;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    collector::NodeReport,
+    nodelog::{Logged, NodeLog},
+    reply::{int, text},
+};
+
+/// One `latency_percentiles_usec_<name>` line of `INFO latencystats` (Redis 7).
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct CommandLatency {
+    pub(crate) name: String,
+    pub(crate) p50: Option<f64>,
+    pub(crate) p99: Option<f64>,
+    /// p99.9
+    pub(crate) p999: Option<f64>,
+}
+
+/// One row of `LATENCY LATEST`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LatencyEvent {
+    /// e.g. `command`, `fork`, `expire-cycle`, `aof-fsync-always`
+    pub(crate) event: String,
+    /// Unix milliseconds of the latest spike.
+    pub(crate) ts: u64,
+    pub(crate) latest_ms: u64,
+    pub(crate) max_ms: u64,
+    /// `LATENCY HISTORY` of the event as (unix milliseconds, ms), oldest first.
+    #[serde(skip)]
+    pub(crate) history: Vec<(u64, u64)>,
+}
+
+/// A latency spike read from a node, for the cluster's spike log.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LatencySpike {
+    pub(crate) ts: u64,
+    /// `ip:port` of the node.
+    pub(crate) node: String,
+    pub(crate) event: String,
+    pub(crate) ms: u64,
+}
+
+/// Parses the reply of `INFO latencystats`, empty before Redis 7.
+pub(crate) fn parse_latencystats(reply: &str) -> Vec<CommandLatency> {
+    let mut commands: Vec<CommandLatency> = reply.lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim_end().split_once(':')?;
+            let name: &str = key.strip_prefix("latency_percentiles_usec_")?;
+            let fields: HashMap<&str, f64> = value.split(',')
+                .filter_map(|item| item.split_once('='))
+                .filter_map(|(k, v)| Some((k, v.parse().ok()?)))
+                .collect();
+            Some(CommandLatency {
+                name: name.to_string(),
+                p50: fields.get("p50").copied(),
+                p99: fields.get("p99").copied(),
+                p999: fields.get("p99.9").copied(),
+            })
+        })
+        .collect();
+    commands.sort_by(|a, b| a.name.cmp(&b.name));
+    commands
+}
+
+/// Parses the reply of `LATENCY LATEST`: `[event, timestamp, latest ms, max ms]` per event.
+pub(crate) fn parse_latency_latest(reply: &Value) -> Vec<LatencyEvent> {
+    let Value::Bulk(rows) = reply else {
+        return Vec::new();
+    };
+    rows.iter()
+        .filter_map(|row| {
+            let Value::Bulk(fields) = row else {
+                return None;
+            };
+            Some(LatencyEvent {
+                event: text(fields.first()?)?,
+                ts: int(fields.get(1)?)? * 1000,
+                latest_ms: int(fields.get(2)?)?,
+                max_ms: int(fields.get(3)?)?,
+                history: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// Parses the reply of `LATENCY HISTORY <event>`: `[timestamp, ms]` per spike.
+pub(crate) fn parse_latency_history(reply: &Value) -> Vec<(u64, u64)> {
+    let Value::Bulk(rows) = reply else {
+        return Vec::new();
+    };
+    rows.iter()
+        .filter_map(|row| match row {
+            Value::Bulk(pair) => Some((int(pair.first()?)? * 1000, int(pair.get(1)?)?)),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Logged for LatencySpike {
+    fn ts(&self) -> u64 {
+        self.ts
+    }
+}
+
+/// Bounded log of the latency spikes reported by the nodes of one cluster.
+#[derive(Default)]
+pub(crate) struct LatencyLog {
+    // Timestamp of the newest spike logged per node and event
+    log: NodeLog<HashMap<String, u64>, LatencySpike>,
+}
+
+impl LatencyLog {
+    /// Logs the spikes newer than the last ones seen on every reachable node.
+    ///
+    /// The first poll of a node backfills what its `LATENCY HISTORY` still holds.
+    pub(crate) fn observe(&self, reports: &[NodeReport]) -> Vec<LatencySpike> {
+        self.log.observe(reports, |report, seen| {
+            let mut spikes: Vec<LatencySpike> = Vec::new();
+            for event in &report.latency {
+                let last: u64 = seen.get(&event.event).copied().unwrap_or_default();
+                let history: Vec<(u64, u64)> = if event.history.is_empty() {
+                    vec![(event.ts, event.latest_ms)]
+                } else {
+                    event.history.clone()
+                };
+                spikes.extend(history.into_iter().filter(|(ts, _)| *ts > last).map(|(ts, ms)| LatencySpike {
+                    ts,
+                    node: report.addr(),
+                    event: event.event.clone(),
+                    ms,
+                }));
+                seen.insert(event.event.clone(), last.max(event.ts));
+            }
+            spikes
+        })
+    }
+
+    /// Timestamp of the newest spike logged per event of `node`, `None`
+    /// until the node has been observed.
+    pub(crate) fn seen(&self, node: &str) -> Option<HashMap<String, u64>> {
+        self.log.state(node, HashMap::clone)
+    }
+
+    /// Logged spikes newer than `since` (unix milliseconds).
+    pub(crate) fn since(&self, since: u64) -> Vec<LatencySpike> {
+        self.log.since(since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::NodeProbe, RedisInstance};
+
+    fn data(s: &str) -> Value {
+        Value::Data(s.as_bytes().to_vec())
+    }
+
+    fn up(latency: Vec<LatencyEvent>) -> NodeReport {
+        let instance = RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() };
+        let mut report = NodeReport::new(&instance, "master", None, Ok(NodeProbe::default()));
+        report.latency = latency;
+        report
+    }
+
+    fn event(ts: u64, history: Vec<(u64, u64)>) -> LatencyEvent {
+        LatencyEvent { event: "command".to_string(), ts, latest_ms: 250, max_ms: 900, history }
+    }
+
+    #[test]
+    fn latest_events_and_their_history_are_parsed() {
+        let latest = Value::Bulk(vec![
+            Value::Bulk(vec![data("command"), Value::Int(1700000000), Value::Int(250), Value::Int(900)]),
+            Value::Bulk(vec![data("fork"), data("1700000005"), Value::Int(12)]),
+        ]);
+        let events: Vec<LatencyEvent> = parse_latency_latest(&latest);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].event.as_str(), events[0].ts, events[0].latest_ms, events[0].max_ms), ("command", 1700000000000, 250, 900));
+
+        let history = Value::Bulk(vec![
+            Value::Bulk(vec![Value::Int(1699999990), Value::Int(900)]),
+            Value::Bulk(vec![Value::Int(1700000000), Value::Int(250)]),
+        ]);
+        assert_eq!(parse_latency_history(&history), [(1699999990000, 900), (1700000000000, 250)]);
+        assert!(parse_latency_history(&Value::Nil).is_empty());
+    }
+
+    #[test]
+    fn the_history_is_backfilled_once_and_then_only_new_spikes_are_logged() {
+        let log = LatencyLog::default();
+        assert_eq!(log.seen("10.0.0.1:6379"), None);
+        let backfill: Vec<LatencySpike> = log.observe(&[up(vec![event(3000, vec![(1000, 900), (3000, 250)])])]);
+        assert_eq!(backfill.iter().map(|s| s.ts).collect::<Vec<_>>(), [1000, 3000]);
+        assert_eq!(log.seen("10.0.0.1:6379").unwrap()["command"], 3000);
+
+        assert!(log.observe(&[up(vec![event(3000, Vec::new())])]).is_empty());
+        let next: Vec<LatencySpike> = log.observe(&[up(vec![event(5000, Vec::new())])]);
+        assert_eq!(next.iter().map(|s| (s.ts, s.ms)).collect::<Vec<_>>(), [(5000, 250)]);
+        assert_eq!(log.since(1000).len(), 2);
+    }
+}
@@ -35,6 +35,9 @@ pub const INDEX_HTML: &str = r#"
       background: #e0f0ff;
       box-shadow: 0 4px 16px #0074d922;
     }
+    .alert-badge { float: right; font-size: .8rem; font-weight: 600; padding: .2rem .55rem; border-radius: 10px; margin-left: .4rem; }
+    .alert-badge.firing { background: #ff4136; color: #fff; }
+    .alert-badge.pending { background: #ff851b33; color: #d35400; }
   </style>
 </head>
 <body>
@@ -53,10 +56,32 @@ pub const INDEX_HTML: &str = r#"
         const ul = document.getElementById('cluster-list');
         data.clusters.forEach(cluster => {
           const li = document.createElement('li');
-          li.innerHTML = `<a class="cluster-link" href="/${cluster.name}">${cluster.name}</a>`;
+          li.innerHTML = `<a class="cluster-link" href="/${cluster.name}">${cluster.name}<span class="alert-badges" data-cluster="${cluster.name}"></span></a>`;
           ul.appendChild(li);
         });
+        refreshAlerts();
+        setInterval(refreshAlerts, 5000);
       });
+
+    // Firing and pending alert counts per cluster
+    function refreshAlerts() {
+      fetch('/alerts')
+        .then(res => res.json())
+        .then(data => {
+          const counts = {};
+          (data.alerts || []).forEach(a => {
+            const c = counts[a.cluster] = counts[a.cluster] || { firing: 0, pending: 0 };
+            c[a.state] = (c[a.state] || 0) + 1;
+          });
+          document.querySelectorAll('.alert-badges').forEach(el => {
+            const c = counts[el.dataset.cluster] || {};
+            el.innerHTML =
+              (c.firing ? `<span class="alert-badge firing" title="firing alerts">${c.firing} firing</span>` : '') +
+              (c.pending ? `<span class="alert-badge pending" title="pending alerts">${c.pending} pending</span>` : '');
+          });
+        })
+        .catch(() => {});
+    }
   </script>
 </body>
 </html>
@@ -104,6 +129,93 @@ pub const CLUSTER_HTML: &str = r#"
     .info-col { flex: 1 1 0; min-width: 280px; }
     .replica-card { border: 1px solid var(--border); border-radius: 10px; padding: .6rem .75rem; margin-bottom: .6rem; background: #fff; }
     .replica-title { font-weight: 600; margin: 0 0 .35rem; }
+
+    /* node reachability */
+    .status-badge { display: inline-block; font-size: .75rem; font-weight: 600; padding: .1rem .45rem; border-radius: 6px; margin-left: .4rem; vertical-align: middle; }
+    .status-down { background: #ff41361a; color: #c0392b; }
+    .status-loading { background: #ff851b1a; color: #d35400; }
+    .status-timeout { background: #b10dc91a; color: #8e44ad; }
+    .status-error { font-size: .85rem; color: #c0392b; margin-left: .4rem; }
+    .replica-card.down { border-color: #ff413666; }
+    .unreachable-list { margin-bottom: 1rem; }
+
+    /* sentinel panel */
+    .sentinel-panel { margin-bottom: 1.5rem; }
+    .sentinel-grid { display: flex; gap: .75rem; flex-wrap: wrap; }
+    .sentinel-card { flex: 1 1 0; min-width: 280px; }
+    .sentinel-card .instance-info-table th { width: auto; }
+    .flag-bad { color: #c0392b; font-weight: 600; }
+    .flag-ok { color: #2e8b57; }
+
+    /* alerts */
+    .alerts-panel { margin-bottom: 1rem; }
+    .alert-item { border-radius: 8px; padding: .45rem .75rem; margin-bottom: .4rem; border: 1px solid; }
+    .alert-item.firing { background: #ff41361a; border-color: #ff4136; }
+    .alert-item.pending { background: #ff851b14; border-color: #ff851b88; }
+    .alert-item .muted { margin-left: .5rem; }
+
+    /* cluster health banner */
+    .cluster-banner { border-radius: 10px; padding: .6rem .9rem; margin-bottom: 1rem; border: 1px solid; }
+    .cluster-banner.ok { background: #2ecc401a; border-color: #2ecc4066; }
+    .cluster-banner.fail { background: #ff41361a; border-color: #ff4136; color: #c0392b; }
+    .cluster-banner .muted { margin-left: .6rem; }
+
+    /* topology consistency */
+    .consistency-panel { margin-bottom: 1.5rem; }
+    .consistency-list { margin: .4rem 0 0; padding-left: 1.2rem; }
+    .consistency-kind { display: inline-block; min-width: 6.5rem; font-weight: 600; }
+
+    /* event log */
+    .events-panel { margin-bottom: 1.5rem; }
+
+    /* memory detail */
+    .memory-detail { flex: 1 1 0; min-width: 420px; }
+    .memory-detail .instance-info-table th { width: 220px; }
+    .memory-doctor { white-space: pre-wrap; font-size: .85rem; background: var(--card-bg); border-radius: 6px; padding: .5rem .75rem; margin: .4rem 0 0; }
+
+    /* key growth */
+    .keys-panel { margin-bottom: 1.5rem; }
+    .keys-chart { height: 240px; }
+
+    /* client list */
+    .clients-panel { margin-bottom: 1.5rem; }
+    .clients-grid { display: flex; gap: 1rem; flex-wrap: wrap; align-items: flex-start; }
+    .clients-grid > div { flex: 1 1 0; min-width: 260px; }
+    .clients-grid h4, .clients-panel h4 { margin: .6rem 0 .3rem; }
+    .clients-panel tr[data-by] { cursor: pointer; }
+    .clients-panel tr[data-by]:hover { background: #0074d90d; }
+    .clients-idle { display: flex; gap: .3rem; align-items: flex-end; height: 60px; }
+    .clients-idle div { flex: 1 1 0; background: #0074d966; border-radius: 3px 3px 0 0; min-height: 1px; }
+    .clients-idle-labels { display: flex; gap: .3rem; font-size: .75rem; }
+    .clients-idle-labels span { flex: 1 1 0; text-align: center; }
+    .clients-drill { max-height: 360px; overflow-y: auto; margin-top: .6rem; }
+
+    /* slow queries */
+    .slowlog-panel { margin-bottom: 1.5rem; }
+    .slowlog-filters { display: flex; gap: 1rem; margin-bottom: .5rem; }
+    .slowlog-scroll { max-height: 420px; overflow-y: auto; }
+    .slowlog-args { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: .85rem; word-break: break-all; }
+
+    /* latency monitor */
+    .latency-panel { margin-bottom: 1.5rem; }
+    .latency-panel h4 { margin: .8rem 0 .4rem; }
+    .latency-container { flex-direction: column; align-items: stretch; }
+    .latency-container select { margin-left: .3rem; }
+
+    /* top commands */
+    .commands-row { display: flex; gap: 1rem; margin-top: 1rem; flex-wrap: wrap; align-items: flex-start; }
+    .commands-row .chart-container { max-width: 520px; height: 260px; }
+    .commands-row .chart-container canvas { height: 220px !important; }
+    .repl-summary { margin-top: .4rem; }
+    .commands-table { flex: 1 1 0; min-width: 420px; max-height: 280px; overflow-y: auto; }
+    .commands-table th[data-key] { cursor: pointer; user-select: none; white-space: nowrap; }
+    .commands-table th.sorted { color: var(--brand); }
+
+    /* slot coverage */
+    .slots-panel { margin-bottom: 1.5rem; }
+    .slots-bar { width: 100%; height: 28px; border: 1px solid var(--border); border-radius: 6px; display: block; }
+    .slots-legend { display: flex; flex-wrap: wrap; gap: .4rem 1rem; margin: .5rem 0; font-size: .85rem; }
+    .slots-swatch { display: inline-block; width: .8rem; height: .8rem; border-radius: 3px; margin-right: .3rem; vertical-align: middle; }
   </style>
   <script src="https://cdn.jsdelivr.net/npm/chart.js"></script>
 </head>
@@ -112,7 +224,67 @@ pub const CLUSTER_HTML: &str = r#"
     <a class="home-link" href="/">Mellow Redis Dashboard</a>
   </header>
   <main>
+    <section id="alerts-panel" class="alerts-panel" style="display:none;"></section>
+    <div id="cluster-banner" class="cluster-banner" style="display:none;"></div>
+    <section id="consistency-panel" class="consistency-panel" style="display:none;">
+      <h2>Topology consistency <span id="consistency-summary"></span></h2>
+      <ul id="consistency-list" class="consistency-list"></ul>
+    </section>
+    <section id="sentinel-panel" class="sentinel-panel" style="display:none;">
+      <h2>Sentinels</h2>
+      <div id="sentinel-grid" class="sentinel-grid"></div>
+    </section>
+    <section id="slots-panel" class="slots-panel" style="display:none;">
+      <h2>Slots <span id="slots-summary" class="muted"></span></h2>
+      <canvas id="slots-bar" class="slots-bar" width="1024" height="28"></canvas>
+      <div id="slots-legend" class="slots-legend"></div>
+      <div id="slots-uncovered" class="flag-bad"></div>
+      <div id="slots-migrations"></div>
+    </section>
+    <section id="latency-panel" class="latency-panel" style="display:none;">
+      <h2>Latency events</h2>
+      <table class="history-table">
+        <thead><tr><th>node</th><th>event</th><th>latest spike</th><th>latest (ms)</th><th>max (ms)</th></tr></thead>
+        <tbody id="latency-tbody"></tbody>
+      </table>
+      <h4 class="muted">Recent spikes</h4>
+      <table class="history-table">
+        <thead><tr><th>time</th><th>node</th><th>event</th><th>ms</th></tr></thead>
+        <tbody id="latency-spikes-tbody"></tbody>
+      </table>
+    </section>
+    <section id="clients-panel" class="clients-panel" style="display:none;">
+      <h2>Clients <span id="clients-summary" class="muted"></span></h2>
+      <label class="muted">node <select id="clients-node"></select></label>
+      <div id="clients-body"></div>
+      <div id="clients-drill" class="clients-drill"></div>
+    </section>
+    <section id="slowlog-panel" class="slowlog-panel" style="display:none;">
+      <h2>Slow queries <span id="slowlog-summary" class="muted"></span></h2>
+      <div class="slowlog-filters">
+        <label class="muted">node <select id="slowlog-node"><option value="">all</option></select></label>
+        <label class="muted">command <select id="slowlog-command"><option value="">all</option></select></label>
+      </div>
+      <div class="slowlog-scroll">
+        <table class="history-table">
+          <thead><tr><th>time</th><th>node</th><th>duration (ms)</th><th>command</th><th>client</th></tr></thead>
+          <tbody id="slowlog-tbody"></tbody>
+        </table>
+      </div>
+    </section>
+    <section class="events-panel">
+      <h2>Events</h2>
+      <table class="history-table">
+        <thead><tr><th>time</th><th>node</th><th>event</th><th>details</th></tr></thead>
+        <tbody id="events-tbody"><tr><td colspan="4" class="muted">No events yet</td></tr></tbody>
+      </table>
+    </section>
+    <section id="keys-panel" class="keys-panel" style="display:none;">
+      <h2>Keys per master</h2>
+      <div class="keys-chart"><canvas id="keys-total-chart"></canvas></div>
+    </section>
     <h2>Instances</h2>
+    <div id="unreachable-list" class="unreachable-list"></div>
     <div id="masters-list"></div>
   </main>
   <script>
@@ -132,8 +304,72 @@ pub const CLUSTER_HTML: &str = r#"
       return n + ' B';
     }
 
-    // Per-master time-series state (keyed by index in current list)
-    const mastersState = {}; // idx -> { labels, cmd, cpuSys, cpuUsr, mem, memRss, prev, prevTime, history, charts }
+    const escapeHtml = (s) => String(s ?? '').replace(/[&<>"']/g, (c) => ({ '&':'&amp;', '<':'&lt;', '>':'&gt;', '"':'&quot;', "'":'&#39;' }[c]));
+    const isUnreachable = (item) => item.status === 'down' || item.status === 'timeout';
+    function statusHtml(item) {
+      const status = item.status || 'up';
+      if (status === 'up') return '';
+      const msg = item.error ? `<span class="status-error">${escapeHtml(item.error.message)}</span>` : '';
+      return `<span class="status-badge status-${status}">${status.toUpperCase()}</span>${msg}`;
+    }
+
+    // Flags from the cluster's own view (CLUSTER NODES) that deserve attention
+    function clusterHtml(item) {
+      const c = item.cluster;
+      if (!c) return '';
+      const flags = (c.flags || []).filter((f) => !['myself', 'master', 'slave', 'replica', 'noflags'].includes(f));
+      if (c.link_state && c.link_state !== 'connected') flags.push(`link ${c.link_state}`);
+      return flags.map((f) => {
+        const cls = f === 'fail?' || f === 'handshake' ? 'status-loading' : 'status-down';
+        return `<span class="status-badge ${cls}" title="node ${escapeHtml(c.id)}">${escapeHtml(f.toUpperCase())}</span>`;
+      }).join('');
+    }
+
+    // Failovers, restarts and topology changes of this cluster, oldest first
+    let eventLog = [];
+    const MAX_EVENTS = 200;
+    const EVENT_COLORS = { role_change: '#ff4136', restart: '#ff4136', uptime_reset: '#ff851b', master_change: '#ff851b', link_down: '#b10dc9', replica_added: '#2ecc40', replica_removed: '#aaaaaa',
+      bgsave_started: '#39cccc', bgsave_finished: '#39cccc', aof_rewrite_started: '#3d9970', aof_rewrite_finished: '#3d9970', persistence_failed: '#ff4136',
+      full_sync: '#ff851b' };
+
+    // Chart.js plugin drawing a dashed vertical line for every event of the block's nodes
+    const eventMarkers = {
+      id: 'eventMarkers',
+      afterDatasetsDraw(chart) {
+        const s = chart.$state;
+        if (!s || !s.ts.length) return;
+        const { ctx, chartArea, scales } = chart;
+        ctx.save();
+        ctx.setLineDash([4, 3]);
+        eventLog.forEach((e) => {
+          if (!s.addrs.has(e.node) || e.ts < s.ts[0]) return;
+          const i = s.ts.findIndex((t) => t >= e.ts);
+          if (i < 0) return;
+          const x = scales.x.getPixelForValue(i);
+          ctx.strokeStyle = EVENT_COLORS[e.kind] || '#111';
+          ctx.beginPath(); ctx.moveTo(x, chartArea.top); ctx.lineTo(x, chartArea.bottom); ctx.stroke();
+        });
+        ctx.restore();
+      }
+    };
+
+    function addEvents(events) {
+      if (!events || !events.length) return;
+      eventLog = eventLog.concat(events).slice(-MAX_EVENTS);
+      const rows = eventLog.slice(-20).reverse().map((e) => `
+        <tr>
+          <td>${new Date(e.ts).toLocaleTimeString()}</td>
+          <td>${escapeHtml(e.node)}</td>
+          <td style="color:${EVENT_COLORS[e.kind] || 'inherit'}">${escapeHtml(e.kind)}</td>
+          <td>${escapeHtml(e.message)}</td>
+        </tr>`).join('');
+      byId('events-tbody').innerHTML = rows;
+    }
+
+    // Per-master time-series state, keyed by a number assigned to each master address on first sight
+    const mastersState = {}; // idx -> { labels, cmd, cpuSys, cpuUsr, mem, memRss, history, lastTs, charts }
+    const masterKeys = {}; // `ip:port` -> idx
+    let nextMasterKey = 0;
 
     function ensureMasterDOM(idx, ip, port, info) {
       const host = `${ip ?? ''}:${port ?? ''}`;
@@ -144,6 +380,13 @@ pub const CLUSTER_HTML: &str = r#"
       const cmdId = `commandsChart-${idx}`;
       const cpuId = `cpuChart-${idx}`;
       const memId = `memoryChart-${idx}`;
+      const keysId = `keysChart-${idx}`;
+      const memDetailId = `memoryDetailChart-${idx}`;
+      const topId = `topCommandsChart-${idx}`;
+      const latencyId = `latencyChart-${idx}`;
+      const replLagId = `replLagChart-${idx}`;
+      const replIoId = `replIoChart-${idx}`;
+      const syncId = `syncChart-${idx}`;
       const histBodyId = `history-tbody-${idx}`;
       const instBodyId = `instance-tbody-${idx}`;
       const replicasColId = `replicas-col-${idx}`;
@@ -152,11 +395,12 @@ pub const CLUSTER_HTML: &str = r#"
       block.className = 'master-block';
       block.id = blockId;
       block.innerHTML = `
-        <h3>Master ${host}</h3>
+        <h3>Master <span id="master-host-${idx}">${host}</span><span id="master-status-${idx}"></span></h3>
         <div class="charts-row">
           <div class="chart-container"><canvas id="${cmdId}"></canvas></div>
           <div class="chart-container"><canvas id="${cpuId}"></canvas></div>
           <div class="chart-container"><canvas id="${memId}"></canvas></div>
+          <div class="chart-container"><canvas id="${keysId}"></canvas></div>
         </div>
         <div style="overflow-x:auto;">
           <table class="history-table">
@@ -182,6 +426,34 @@ pub const CLUSTER_HTML: &str = r#"
           </table>
         </div>
 
+        <div class="commands-row">
+          <div class="chart-container"><canvas id="${topId}"></canvas></div>
+          <div class="chart-container latency-container">
+            <label class="muted">latency (usec) of<select id="latency-cmd-${idx}"></select></label>
+            <canvas id="${latencyId}"></canvas>
+          </div>
+          <div class="commands-table">
+            <table class="history-table">
+              <thead><tr id="commands-head-${idx}">${COMMAND_COLUMNS.map((c) => `<th data-key="${c.key}">${c.label}</th>`).join('')}</tr></thead>
+              <tbody id="commands-tbody-${idx}">
+                <tr><td colspan="${COMMAND_COLUMNS.length}" class="muted">Waiting for data...</td></tr>
+              </tbody>
+            </table>
+            <div id="errors-${idx}" class="muted" style="margin-top:.4rem;"></div>
+          </div>
+        </div>
+
+        <div class="commands-row">
+          <div class="chart-container"><canvas id="${memDetailId}"></canvas></div>
+          <div class="memory-detail" id="memory-detail-${idx}"></div>
+        </div>
+
+        <div class="commands-row" id="replication-row-${idx}" style="display:none;">
+          <div class="chart-container"><canvas id="${replLagId}"></canvas></div>
+          <div class="chart-container"><canvas id="${replIoId}"></canvas></div>
+          <div class="chart-container"><canvas id="${syncId}"></canvas></div>
+        </div>
+
         <div class="info-row" style="margin-top:1rem;">
           <div class="info-col">
             <h4 class="muted" style="margin:0 0 .4rem;">Instance information</h4>
@@ -197,9 +469,12 @@ pub const CLUSTER_HTML: &str = r#"
                 <tr><th>port</th><td>${port ?? ''}</td></tr>
               </tbody>
             </table>
+            <h4 class="muted" style="margin:.8rem 0 .4rem;">Persistence</h4>
+            <div id="persistence-${idx}"></div>
           </div>
           <div class="info-col">
             <h4 class="muted" style="margin:0 0 .4rem;">Replicas</h4>
+            <div id="links-${idx}" style="margin-bottom:.6rem;"></div>
             <div id="${replicasColId}"></div>
           </div>
         </div>
@@ -216,7 +491,8 @@ pub const CLUSTER_HTML: &str = r#"
       s.charts.commands = new Chart(cmdCtx, {
         type: 'line',
         data: { labels: s.labels, datasets: [{ label: 'cmd/s', data: s.cmd, borderColor: 'rgba(255,133,27,0.9)', backgroundColor: 'rgba(255,133,27,0.18)', fill: true, tension: 0.35, pointRadius: 0 }] },
-        options: { responsive: true, scales: { y: { beginAtZero: true } } }
+        options: { responsive: true, scales: { y: { beginAtZero: true } } },
+        plugins: [eventMarkers]
       });
       s.charts.cpu = new Chart(cpuCtx, {
         type: 'line',
@@ -227,7 +503,8 @@ pub const CLUSTER_HTML: &str = r#"
             { label: 'cpu_usr', data: s.cpuUsr, borderColor: 'rgba(46,204,64,0.9)', backgroundColor: 'rgba(46,204,64,0.18)', fill: true, tension: 0.35, pointRadius: 0 }
           ]
         },
-        options: { responsive: true, scales: { y: { beginAtZero: true } } }
+        options: { responsive: true, scales: { y: { beginAtZero: true } } },
+        plugins: [eventMarkers]
       });
       s.charts.mem = new Chart(memCtx, {
         type: 'line',
@@ -238,11 +515,149 @@ pub const CLUSTER_HTML: &str = r#"
             { label: 'mem_rss', data: s.memRss, borderColor: 'rgba(177,13,201,0.9)', backgroundColor: 'rgba(177,13,201,0.18)', fill: true, tension: 0.35, pointRadius: 0 }
           ]
         },
-        options: { responsive: true, scales: { y: { beginAtZero: true, ticks: { callback: (v) => formatBytes(v) } } } }
+        options: { responsive: true, scales: { y: { beginAtZero: true, ticks: { callback: (v) => formatBytes(v) } } } },
+        plugins: [eventMarkers]
+      });
+      s.charts.keys = new Chart(byId(keysId).getContext('2d'), {
+        type: 'line',
+        data: { labels: s.labels, datasets: keyDatasets(s) },
+        options: { responsive: true, scales: { y: { beginAtZero: true } } },
+        plugins: [eventMarkers]
+      });
+      s.charts.memDetail = new Chart(byId(memDetailId).getContext('2d'), {
+        type: 'line',
+        data: {
+          labels: s.labels,
+          datasets: [
+            { label: 'maxmemory used %', data: s.memUtil, borderColor: 'rgba(0,116,217,0.9)', backgroundColor: 'rgba(0,116,217,0.18)', fill: true, tension: 0.35, pointRadius: 0, yAxisID: 'y' },
+            { label: 'fragmentation ratio', data: s.frag, borderColor: 'rgba(177,13,201,0.9)', backgroundColor: 'rgba(177,13,201,0.18)', fill: false, tension: 0.35, pointRadius: 0, yAxisID: 'ratio' }
+          ]
+        },
+        options: {
+          responsive: true,
+          scales: {
+            y: { beginAtZero: true, suggestedMax: 100, ticks: { callback: (v) => `${v}%` } },
+            ratio: { position: 'right', beginAtZero: true, grid: { drawOnChartArea: false } }
+          }
+        },
+        plugins: [eventMarkers]
+      });
+      s.charts.replLag = new Chart(byId(replLagId).getContext('2d'), {
+        type: 'line',
+        data: { labels: s.labels, datasets: [] },
+        options: { responsive: true, scales: { y: { beginAtZero: true, title: { display: true, text: 'bytes behind master' }, ticks: { callback: (v) => formatBytes(v) } } } },
+        plugins: [eventMarkers]
+      });
+      s.charts.replIo = new Chart(byId(replIoId).getContext('2d'), {
+        type: 'line',
+        data: { labels: s.labels, datasets: [] },
+        options: { responsive: true, scales: { y: { beginAtZero: true, title: { display: true, text: 'last io with master (s)' } } } },
+        plugins: [eventMarkers]
+      });
+      s.charts.sync = new Chart(byId(syncId).getContext('2d'), {
+        type: 'line',
+        data: {
+          labels: s.labels,
+          datasets: [
+            { label: 'full syncs', data: s.syncFull, borderColor: 'rgba(255,65,54,0.9)', backgroundColor: 'rgba(255,65,54,0.18)', fill: false, stepped: true, pointRadius: 0 },
+            { label: 'partial ok', data: s.syncOk, borderColor: 'rgba(46,204,64,0.9)', backgroundColor: 'rgba(46,204,64,0.18)', fill: false, stepped: true, pointRadius: 0 },
+            { label: 'partial err', data: s.syncErr, borderColor: 'rgba(255,133,27,0.9)', backgroundColor: 'rgba(255,133,27,0.18)', fill: false, stepped: true, pointRadius: 0 }
+          ]
+        },
+        options: { responsive: true, scales: { y: { beginAtZero: true, ticks: { precision: 0 } } } },
+        plugins: [eventMarkers]
+      });
+      s.charts.top = new Chart(byId(topId).getContext('2d'), {
+        type: 'line',
+        data: { labels: s.cmdLabels, datasets: [] },
+        options: { responsive: true, animation: false, scales: { y: { stacked: true, beginAtZero: true, title: { display: true, text: 'calls/s' } } } }
       });
+      s.charts.latency = new Chart(byId(latencyId).getContext('2d'), {
+        type: 'line',
+        data: { labels: s.latLabels, datasets: [] },
+        options: { responsive: true, animation: false, scales: { y: { beginAtZero: true } } }
+      });
+      byId(`latency-cmd-${idx}`).addEventListener('change', (e) => {
+        s.latCommand = e.target.value;
+        renderLatency(idx);
+      });
+      byId(`commands-head-${idx}`).addEventListener('click', (e) => {
+        const key = e.target.dataset && e.target.dataset.key;
+        if (!key) return;
+        s.cmdSort = { key, desc: s.cmdSort.key === key ? !s.cmdSort.desc : key !== 'name' };
+        renderCommands(idx);
+      });
+      Object.values(s.charts).forEach((chart) => { chart.$state = s; });
+    }
+
+    // Breakdown of `MEMORY STATS` and the `MEMORY DOCTOR` report of one node
+    const MEMORY_FIELDS = [
+      ['total.allocated', 'total allocated', 'bytes'],
+      ['peak.allocated', 'peak allocated', 'bytes'],
+      ['dataset.bytes', 'dataset', 'bytes'],
+      ['overhead.total', 'overhead', 'bytes'],
+      ['startup.allocated', 'startup', 'bytes'],
+      ['replication.backlog', 'replication backlog', 'bytes'],
+      ['clients.normal', 'clients (normal)', 'bytes'],
+      ['clients.slaves', 'clients (replicas)', 'bytes'],
+      ['aof.buffer', 'AOF buffer', 'bytes'],
+      ['lua.caches', 'Lua caches', 'bytes'],
+      ['keys.count', 'keys', 'count'],
+      ['keys.bytes-per-key', 'bytes per key', 'count'],
+      ['allocator.allocated', 'allocator allocated', 'bytes'],
+      ['allocator.active', 'allocator active', 'bytes'],
+      ['allocator.resident', 'allocator resident', 'bytes'],
+      ['allocator-fragmentation.ratio', 'allocator fragmentation', 'ratio'],
+      ['allocator-fragmentation.bytes', 'allocator fragmentation', 'bytes'],
+      ['fragmentation', 'fragmentation ratio', 'ratio'],
+      ['fragmentation.bytes', 'fragmentation', 'bytes'],
+    ];
+
+    function memoryHtml(item) {
+      const info = item.info || {};
+      const stats = item.memory_stats || {};
+      const used = Number(info.used_memory), max = Number(info.maxmemory);
+      const fmt = (v, kind) => kind === 'bytes' ? formatBytes(v) : kind === 'ratio' ? fmtF2(v) : fmtI(v);
+      const rows = [
+        `<tr><th>used / maxmemory</th><td class="num">${formatBytes(used)} / ${max > 0 ? `${formatBytes(max)} (${fmtF2(used / max * 100)}%)` : 'no limit'}</td></tr>`,
+        ...MEMORY_FIELDS.filter(([key]) => stats[key] != null)
+          .map(([key, label, kind]) => `<tr><th title="${key}">${label}</th><td class="num">${fmt(stats[key], kind)}</td></tr>`),
+      ];
+      return `
+        <table class="instance-info-table"><tbody>${rows.join('')}</tbody></table>
+        ${item.memory_doctor ? `<details><summary class="muted">MEMORY DOCTOR</summary><pre class="memory-doctor">${escapeHtml(item.memory_doctor)}</pre></details>` : ''}`;
+    }
+
+    // INFO Persistence of one node, with the warnings computed by the server
+    function persistenceWarningsHtml(item) {
+      return (item.persistence_warnings || []).map((w) => `<div class="flag-bad">⚠ ${escapeHtml(w)}</div>`).join('');
     }
 
-    function ensureReplicaCard(masterIdx, repKey, ip, port, info) {
+    function persistenceHtml(item) {
+      const info = item.info || {};
+      const status = (v) => v == null ? '' : `<span class="${v === 'ok' ? 'flag-ok' : 'flag-bad'}">${escapeHtml(v)}</span>`;
+      const running = (v) => v === '1' ? '<span class="flag-bad">running</span>' : 'no';
+      const lastSave = Number(info.rdb_last_save_time);
+      const rows = [
+        ['loading', info.loading === '1' ? '<span class="flag-bad">yes</span>' : 'no'],
+        ['rdb_bgsave_in_progress', running(info.rdb_bgsave_in_progress)],
+        ['rdb_last_bgsave_status', status(info.rdb_last_bgsave_status)],
+        ['rdb_last_save_time', lastSave ? new Date(lastSave * 1000).toLocaleString() : ''],
+        ['rdb_last_bgsave_time_sec', fmtI(info.rdb_last_bgsave_time_sec)],
+        ['rdb_changes_since_last_save', fmtI(info.rdb_changes_since_last_save)],
+        ['aof_enabled', info.aof_enabled === '1' ? 'yes' : 'no'],
+        ...(info.aof_enabled === '1' ? [
+          ['aof_rewrite_in_progress', running(info.aof_rewrite_in_progress)],
+          ['aof_last_bgrewrite_status', status(info.aof_last_bgrewrite_status)],
+          ['aof_last_write_status', status(info.aof_last_write_status)],
+        ] : []),
+        ['latest_fork_usec', fmtI(info.latest_fork_usec)],
+      ];
+      return `${persistenceWarningsHtml(item)}
+        <table class="instance-info-table"><tbody>${rows.map(([k, v]) => `<tr><th>${k}</th><td>${v}</td></tr>`).join('')}</tbody></table>`;
+    }
+
+    function ensureReplicaCard(masterIdx, repKey, ip, port, info, item) {
       const col = byId(`replicas-col-${masterIdx}`);
       if (!col) return;
       const cardId = `replica-card-${masterIdx}-${repKey}`;
@@ -253,13 +668,19 @@ pub const CLUSTER_HTML: &str = r#"
         card.className = 'replica-card';
         card.id = cardId;
         card.innerHTML = `
-          <div class="replica-title">Replica ${ip ?? ''}:${port ?? ''}</div>
+          <div class="replica-title">Replica ${ip ?? ''}:${port ?? ''}<span id="${cardId}-status"></span></div>
           <table class="instance-info-table">
             <tbody id="${bodyId}"></tbody>
           </table>
+          <div id="${cardId}-warnings"></div>
+          <details id="${cardId}-persistence"></details>
+          <details id="${cardId}-memory"></details>
         `;
         col.appendChild(card);
       }
+      card.classList.toggle('down', isUnreachable(item));
+      const statusEl = byId(`${cardId}-status`);
+      if (statusEl) statusEl.innerHTML = statusHtml(item) + clusterHtml(item);
       const tbody = byId(bodyId);
       if (tbody) {
         tbody.innerHTML = `
@@ -270,59 +691,298 @@ pub const CLUSTER_HTML: &str = r#"
           <tr><th>role</th><td>${info.role ?? 'slave'}</td></tr>
           <tr><th>ip</th><td>${ip ?? ''}</td></tr>
           <tr><th>port</th><td>${port ?? ''}</td></tr>
+          ${replicationRows(item, info)}
         `;
       }
+      const memEl = byId(`${cardId}-memory`);
+      if (memEl && !isUnreachable(item)) {
+        const open = memEl.open;
+        memEl.innerHTML = `<summary class="muted">memory</summary>${memoryHtml(item)}`;
+        memEl.open = open;
+      }
+      const warningsEl = byId(`${cardId}-warnings`);
+      if (warningsEl) warningsEl.innerHTML = persistenceWarningsHtml(item);
+      const persistEl = byId(`${cardId}-persistence`);
+      if (persistEl && !isUnreachable(item)) {
+        const open = persistEl.open;
+        persistEl.innerHTML = `<summary class="muted">persistence</summary>${persistenceHtml({ ...item, persistence_warnings: [] })}`;
+        persistEl.open = open;
+      }
+    }
+
+    // Last hour of server-side samples per node (`ip:port`), loaded before the first event
+    let backfill = {};
+    const MAX_POINTS = 3600;
+
+    function newMasterState(addr) {
+      const s = { labels: [], ts: [], cmd: [], cpuSys: [], cpuUsr: [], mem: [], memRss: [], history: [], lastTs: 0, charts: null, addrs: new Set([addr]),
+        keys: {}, keysTotal: [], memUtil: [], frag: [], repl: {}, backlog: [], syncFull: [], syncOk: [], syncErr: [],
+        commands: [], errors: [], cmdLabels: [], cmdSeries: {}, cmdSort: { key: 'calls_s', desc: true },
+        latLabels: [], latSeries: {}, latCommand: null };
+      (backfill[addr] || []).forEach((sample) => pushSample(s, sample));
+      return s;
+    }
+
+    // Key count per database of the master, one line each
+    const KEY_COLORS = ['#0074d9', '#2ecc40', '#ff851b', '#b10dc9', '#39cccc', '#85144b'];
+    function keyDatasets(s) {
+      return Object.keys(s.keys).sort((a, b) => a - b).map((db, i) => {
+        const color = KEY_COLORS[i % KEY_COLORS.length];
+        return { label: `db${db} keys`, data: s.keys[db], borderColor: color, backgroundColor: color + '2e', fill: false, tension: 0.35, pointRadius: 0 };
+      });
+    }
+
+    // `p` is a server sample, or null to leave a gap while the node is unreachable
+    function pushPoint(s, ts, p) {
+      const keys = p ? (p.keys || {}) : null;
+      Object.keys(keys || {}).forEach((db) => {
+        if (!s.keys[db]) s.keys[db] = new Array(s.labels.length).fill(null);
+      });
+      Object.entries(s.keys).forEach(([db, series]) => {
+        series.push(keys ? (keys[db] ?? 0) : null);
+        if (series.length > MAX_POINTS) series.shift();
+      });
+      s.keysTotal.push(keys ? Object.values(keys).reduce((n, v) => n + v, 0) : null);
+      if (s.keysTotal.length > MAX_POINTS) s.keysTotal.shift();
+      // Filled in by the replicas' samples of the same tick
+      Object.values(s.repl).forEach((r) => {
+        r.lag.push(null); r.io.push(null);
+        if (r.lag.length > MAX_POINTS) { r.lag.shift(); r.io.shift(); }
+      });
+
+      const arrays = [s.labels, s.ts, s.cmd, s.cpuSys, s.cpuUsr, s.mem, s.memRss, s.memUtil, s.frag, s.backlog, s.syncFull, s.syncOk, s.syncErr];
+      s.labels.push(new Date(ts).toLocaleTimeString()); s.ts.push(ts);
+      s.cmd.push(p ? p.cmd_s : null); s.cpuSys.push(p ? p.cpu_sys : null); s.cpuUsr.push(p ? p.cpu_usr : null);
+      s.mem.push(p ? p.mem : null); s.memRss.push(p ? p.mem_rss : null);
+      s.memUtil.push(p && p.mem_util != null ? p.mem_util * 100 : null); s.frag.push(p ? p.frag_ratio : null);
+      s.backlog.push(p ? p.repl_backlog : null);
+      s.syncFull.push(p ? p.sync_full : null); s.syncOk.push(p ? p.sync_partial_ok : null); s.syncErr.push(p ? p.sync_partial_err : null);
+      if (s.labels.length > MAX_POINTS) arrays.forEach((a) => a.shift());
+    }
+
+    // Rates are computed by the server; the first sample of a node has none and is not drawn
+    function pushSample(s, sample) {
+      if (sample.ts <= s.lastTs) return;
+      s.lastTs = sample.ts;
+      const tsLabel = new Date(sample.ts).toLocaleTimeString();
+      pushPoint(s, sample.ts, sample);
+      if (sample.cmd_s != null) {
+        s.history.unshift({ ...sample, time: tsLabel });
+        if (s.history.length > 10) s.history.pop();
+      }
+    }
+
+    // Lag and last io of a replica, charted on its master's timeline; the first
+    // sighting backfills what the server kept for the replica
+    function pushReplica(s, addr, sample) {
+      let r = s.repl[addr];
+      const samples = r ? (sample ? [sample] : []) : (backfill[addr] || []).concat(sample ? [sample] : []);
+      if (!r) r = s.repl[addr] = { lag: new Array(s.labels.length).fill(null), io: new Array(s.labels.length).fill(null) };
+      samples.forEach((p) => {
+        // Nodes of one snapshot share its timestamp
+        const i = s.ts.lastIndexOf(p.ts);
+        if (i < 0) return;
+        r.lag[i] = p.repl_lag;
+        r.io[i] = p.repl_io;
+      });
+    }
+
+    function replicaDatasets(s, key) {
+      return Object.entries(s.repl)
+        .filter(([, r]) => r[key].some((v) => v != null))
+        .map(([addr, r], i) => {
+          const color = KEY_COLORS[i % KEY_COLORS.length];
+          return { label: addr, data: r[key], borderColor: color, backgroundColor: color + '2e', fill: false, tension: 0.35, pointRadius: 0 };
+        });
     }
 
-    function updateMaster(idx, info, ip, port) {
-      if (!mastersState[idx]) {
-        mastersState[idx] = { labels: [], cmd: [], cpuSys: [], cpuUsr: [], mem: [], memRss: [], prev: null, prevTime: 0, history: [], charts: null };
+    function renderReplication(idx) {
+      const s = mastersState[idx];
+      const row = byId(`replication-row-${idx}`);
+      if (!s.charts || !row) return;
+      const syncs = s.syncFull.some((v) => v) || s.syncOk.some((v) => v) || s.syncErr.some((v) => v);
+      row.style.display = Object.keys(s.repl).length || syncs ? '' : 'none';
+      // A replica above the backlog line can only resync in full
+      const backlog = { label: 'master backlog', data: s.backlog, borderColor: 'rgba(133,20,75,0.8)', borderDash: [6, 4], fill: false, stepped: true, pointRadius: 0 };
+      s.charts.replLag.data.datasets = replicaDatasets(s, 'lag').concat(s.backlog.some((v) => v != null) ? [backlog] : []);
+      s.charts.replLag.update();
+      s.charts.replIo.data.datasets = replicaDatasets(s, 'io');
+      s.charts.replIo.update();
+      s.charts.sync.update();
+    }
+
+    // Replication state of a replica, from its own INFO and its master's offset
+    function replicationRows(item, info) {
+      const r = item.replication;
+      if (!r) return '';
+      const link = r.link_status === 'up' ? '<span class="flag-ok">up</span>' : `<span class="flag-bad">${escapeHtml(r.link_status || '?')}</span>`;
+      const rows = [
+        ['master_link_status', link + (r.link_down_secs != null ? ` for ${fmtI(r.link_down_secs)}s` : '')],
+        ['master_last_io_seconds_ago', r.last_io_secs == null ? '' : `<span class="${r.last_io_secs > 10 ? 'flag-bad' : ''}">${fmtI(r.last_io_secs)}</span>`],
+        ['master_sync_in_progress', r.sync_in_progress ? '<span class="flag-bad">full sync</span>' : 'no'],
+        ['slave_repl_offset', fmtI(r.offset)],
+        ['behind master', r.lag_bytes == null ? '<span class="muted">master unreachable</span>' : formatBytes(r.lag_bytes)],
+        ['partial sync possible', r.in_backlog == null ? '' : r.in_backlog ? 'yes' : '<span class="flag-bad">no, beyond the backlog</span>'],
+        ['repl_backlog_histlen', formatBytes(Number(info.repl_backlog_histlen))],
+      ];
+      return rows.map(([k, v]) => `<tr><th>${k}</th><td>${v}</td></tr>`).join('');
+    }
+
+    // Per-command statistics; calls/s is only charted from live snapshots
+    const COMMAND_COLUMNS = [
+      { key: 'name', label: 'command' },
+      { key: 'calls_s', label: 'calls/s' },
+      { key: 'usec_per_call', label: 'usec/call' },
+      { key: 'rejected_s', label: 'rej/s' },
+      { key: 'failed_s', label: 'fail/s' },
+      { key: 'calls', label: 'calls' },
+      { key: 'rejected_calls', label: 'rejected' },
+      { key: 'failed_calls', label: 'failed' },
+    ];
+    const COMMAND_COLORS = ['#ff851b', '#0074d9', '#2ecc40', '#b10dc9', '#39cccc', '#ff4136', '#3d9970', '#85144b'];
+    const TOP_COMMANDS = 8;
+
+    function pushCommands(s, item) {
+      s.commands = item.commands || [];
+      s.errors = item.errors || [];
+      if (!item.sample || !s.commands.some((c) => c.calls_s != null)) return;
+      s.cmdLabels.push(new Date(item.sample.ts).toLocaleTimeString());
+      const len = s.cmdLabels.length;
+      s.commands.forEach((c) => {
+        if (!s.cmdSeries[c.name]) s.cmdSeries[c.name] = new Array(len - 1).fill(0);
+      });
+      const rates = Object.fromEntries(s.commands.map((c) => [c.name, c.calls_s || 0]));
+      Object.entries(s.cmdSeries).forEach(([name, series]) => {
+        series.push(rates[name] || 0);
+        if (series.length > MAX_POINTS) series.shift();
+      });
+      if (s.cmdLabels.length > MAX_POINTS) s.cmdLabels.shift();
+    }
+
+    function renderCommands(idx) {
+      const s = mastersState[idx];
+      const { key, desc } = s.cmdSort;
+      const value = (c) => c[key] ?? -1;
+      const rows = s.commands.slice().sort((a, b) => {
+        const cmp = key === 'name' ? a.name.localeCompare(b.name) : value(a) - value(b);
+        return desc ? -cmp : cmp;
+      });
+      const tbody = byId(`commands-tbody-${idx}`);
+      if (tbody) {
+        tbody.innerHTML = rows.length ? rows.map((c) => `
+          <tr>
+            <td>${escapeHtml(c.name)}</td>
+            <td class="num">${fmtI(c.calls_s)}</td>
+            <td class="num">${fmtF2(c.usec_per_call)}</td>
+            <td class="num ${c.rejected_s ? 'flag-bad' : ''}">${fmtI(c.rejected_s)}</td>
+            <td class="num ${c.failed_s ? 'flag-bad' : ''}">${fmtI(c.failed_s)}</td>
+            <td class="num">${fmtI(c.calls)}</td>
+            <td class="num">${fmtI(c.rejected_calls)}</td>
+            <td class="num">${fmtI(c.failed_calls)}</td>
+          </tr>`).join('') : `<tr><td colspan="${COMMAND_COLUMNS.length}" class="muted">No commands yet</td></tr>`;
+      }
+      const head = byId(`commands-head-${idx}`);
+      if (head) head.querySelectorAll('th').forEach((th) => {
+        const col = COMMAND_COLUMNS.find((c) => c.key === th.dataset.key);
+        th.classList.toggle('sorted', th.dataset.key === key);
+        th.textContent = col.label + (th.dataset.key === key ? (desc ? ' ▼' : ' ▲') : '');
+      });
+      const errorsEl = byId(`errors-${idx}`);
+      if (errorsEl) {
+        errorsEl.innerHTML = s.errors.length ? 'errors: ' + s.errors.map((e) =>
+          `<span class="${e.count_s ? 'flag-bad' : ''}">${escapeHtml(e.name)} ${fmtI(e.count)}${e.count_s ? ` (${fmtI(e.count_s)}/s)` : ''}</span>`
+        ).join(', ') : '';
+      }
+
+      // The busiest commands over the charted window, the rest stacked as "other"
+      const chart = s.charts && s.charts.top;
+      if (!chart) return;
+      const total = (series) => series.reduce((n, v) => n + v, 0);
+      const ranked = Object.entries(s.cmdSeries).sort((a, b) => total(b[1]) - total(a[1]));
+      const datasets = ranked.slice(0, TOP_COMMANDS).map(([name, data], i) => ({ label: name, data, color: COMMAND_COLORS[i % COMMAND_COLORS.length] }));
+      const rest = ranked.slice(TOP_COMMANDS);
+      if (rest.length) {
+        datasets.push({ label: 'other', data: s.cmdLabels.map((_, i) => rest.reduce((n, [, data]) => n + (data[i] || 0), 0)), color: '#aaaaaa' });
       }
+      chart.data.datasets = datasets.map((d, i) => ({
+        label: d.label, data: d.data, borderColor: d.color, backgroundColor: d.color + '55',
+        fill: i === 0 ? 'origin' : '-1', tension: 0.35, pointRadius: 0, borderWidth: 1
+      }));
+      chart.update();
+    }
+
+    // Percentiles of `INFO latencystats` (Redis 7), charted for one command at a time
+    const LATENCY_PERCENTILES = [
+      { key: 'p50', label: 'p50', color: '#2ecc40' },
+      { key: 'p99', label: 'p99', color: '#ff851b' },
+      { key: 'p999', label: 'p99.9', color: '#ff4136' },
+    ];
+
+    function pushLatency(s, item) {
+      const stats = item.latency_stats || [];
+      if (!item.sample || !stats.length) return;
+      s.latLabels.push(new Date(item.sample.ts).toLocaleTimeString());
+      const len = s.latLabels.length;
+      const byName = Object.fromEntries(stats.map((l) => [l.name, l]));
+      stats.forEach((l) => {
+        if (!s.latSeries[l.name]) {
+          s.latSeries[l.name] = Object.fromEntries(LATENCY_PERCENTILES.map((p) => [p.key, new Array(len - 1).fill(null)]));
+        }
+      });
+      Object.entries(s.latSeries).forEach(([name, series]) => {
+        LATENCY_PERCENTILES.forEach((p) => {
+          series[p.key].push(byName[name] ? byName[name][p.key] : null);
+          if (series[p.key].length > MAX_POINTS) series[p.key].shift();
+        });
+      });
+      if (s.latLabels.length > MAX_POINTS) s.latLabels.shift();
+    }
+
+    function renderLatency(idx) {
       const s = mastersState[idx];
-      const now = Date.now();
-      const tsLabel = new Date(now).toLocaleTimeString();
-
-      const n = (x) => (x==null || isNaN(Number(x))) ? 0 : Number(x);
-      const dt = s.prevTime ? Math.max((now - s.prevTime) / 1000, 0.001) : null;
-
-      // raw CPU (no per-second)
-      const cpu_sys_raw = n(info.used_cpu_sys);
-      const cpu_usr_raw = n(info.used_cpu_user);
-
-      // per-second rates (rounded to integers). Skip first sample.
-      let cmd_s = null, rej_s = null, exp_s = null, evt_s = null, hit_s = null, mis_s = null;
-      if (s.prev && dt) {
-        const r = (curr, prev) => Math.max(0, Math.round((n(curr) - n(prev)) / dt));
-        cmd_s = r(info.total_commands_processed, s.prev.total_commands_processed);
-        rej_s = r(info.rejected_connections, s.prev.rejected_connections);
-        exp_s = r(info.expired_keys, s.prev.expired_keys);
-        evt_s = r(info.evicted_keys, s.prev.evicted_keys);
-        hit_s = r(info.keyspace_hits, s.prev.keyspace_hits);
-        mis_s = r(info.keyspace_misses, s.prev.keyspace_misses);
+      const names = Object.keys(s.latSeries).sort();
+      const select = byId(`latency-cmd-${idx}`);
+      if (!select) return;
+      // Until one is picked, follow the busiest command
+      const busiest = s.commands.find((c) => s.latSeries[c.name]);
+      const current = s.latCommand || (busiest && busiest.name) || names[0] || '';
+      if (select.dataset.names !== names.join(' ')) {
+        select.dataset.names = names.join(' ');
+        select.innerHTML = names.map((n) => `<option value="${escapeHtml(n)}">${escapeHtml(n)}</option>`).join('');
       }
+      select.value = current;
+      const chart = s.charts && s.charts.latency;
+      if (!chart) return;
+      const series = s.latSeries[current];
+      chart.data.datasets = series ? LATENCY_PERCENTILES.map((p) => ({
+        label: p.label, data: series[p.key], borderColor: p.color, backgroundColor: p.color + '22',
+        fill: false, tension: 0.35, pointRadius: 0, borderWidth: 1.5
+      })) : [];
+      chart.update();
+    }
+
+    function updateMaster(idx, info, ip, port, item) {
+      const s = mastersState[idx];
 
-      // Append to series (first rate is null -> not drawn)
-      s.labels.push(tsLabel);
-      s.cmd.push(cmd_s != null ? cmd_s : null);
-      s.cpuSys.push(cpu_sys_raw);
-      s.cpuUsr.push(cpu_usr_raw);
-      s.mem.push(n(info.used_memory));
-      s.memRss.push(n(info.used_memory_rss));
-      if (s.labels.length > 1800) { s.labels.shift(); s.cmd.shift(); s.cpuSys.shift(); s.cpuUsr.shift(); s.mem.shift(); s.memRss.shift(); }
+      // The master behind a slot can change after a Sentinel failover
+      const hostEl = byId(`master-host-${idx}`);
+      if (hostEl) hostEl.textContent = `${ip ?? ''}:${port ?? ''}`;
+      const statusEl = byId(`master-status-${idx}`);
+      if (statusEl) statusEl.innerHTML = statusHtml(item) + clusterHtml(item);
+      if (!item.sample) {
+        // Leave a gap in the charts while the node is unreachable
+        pushPoint(s, Date.now(), null);
+      } else {
+        pushSample(s, item.sample);
+      }
+      pushCommands(s, item);
+      renderCommands(idx);
+      pushLatency(s, item);
+      renderLatency(idx);
 
-      // Update history table (skip first row without rate)
+      // Update history table (rows without rate are skipped)
       const histBody = byId(`history-tbody-${idx}`);
-      if (cmd_s != null && histBody) {
-        s.history.unshift({
-          time: tsLabel,
-          cpu_usr: cpu_usr_raw,
-          cpu_sys: cpu_sys_raw,
-          clients: info.connected_clients,
-          mem: info.used_memory,
-          mem_rss: info.used_memory_rss,
-          cmd_s, rej_s, exp_s, evt_s, hit_s, mis_s
-        });
-        if (s.history.length > 10) s.history.pop();
+      if (histBody && s.history.length) {
         histBody.innerHTML = s.history.map(r => `
           <tr>
             <td>${r.time}</td>
@@ -339,7 +999,7 @@ pub const CLUSTER_HTML: &str = r#"
             <td class="num">${fmtI(r.mis_s)}</td>
           </tr>
         `).join('');
-      } else if (histBody && !s.history.length) {
+      } else if (histBody) {
         histBody.innerHTML = `<tr><td colspan="12" class="muted">Waiting for data...</td></tr>`;
       }
 
@@ -357,44 +1017,428 @@ pub const CLUSTER_HTML: &str = r#"
           <tr><th>connected_slaves</th><td>${info.connected_slaves ?? ''}</td></tr>
           <tr><th>ip</th><td>${ipDisp}</td></tr>
           <tr><th>port</th><td>${portDisp}</td></tr>
+          ${(item.keyspace || []).map((d) => `
+            <tr><th>db${d.db}</th><td>${fmtI(d.keys)} keys, ${fmtI(d.expires)} expiring, avg_ttl ${fmtI(d.avg_ttl)} ms</td></tr>`).join('')}
         `;
       }
 
-      // Save snapshot for next diff
-      mastersState[idx].prev = {
-        total_commands_processed: info.total_commands_processed,
-        used_cpu_sys: info.used_cpu_sys,
-        used_cpu_user: info.used_cpu_user,
-        rejected_connections: info.rejected_connections,
-        expired_keys: info.expired_keys,
-        evicted_keys: info.evicted_keys,
-        keyspace_hits: info.keyspace_hits,
-        keyspace_misses: info.keyspace_misses
-      };
-      mastersState[idx].prevTime = now;
+      const persistEl = byId(`persistence-${idx}`);
+      if (persistEl) persistEl.innerHTML = isUnreachable(item) ? '' : persistenceHtml(item);
+
+      const memDetail = byId(`memory-detail-${idx}`);
+      if (memDetail && !isUnreachable(item)) {
+        // Keep the doctor report open across refreshes
+        const doctor = memDetail.querySelector('details');
+        const open = doctor && doctor.open;
+        memDetail.innerHTML = memoryHtml(item);
+        const refreshed = memDetail.querySelector('details');
+        if (refreshed) refreshed.open = !!open;
+      }
+
+      // Replication links from the master's point of view
+      const linksEl = byId(`links-${idx}`);
+      if (linksEl) {
+        const links = item.replicas || [];
+        const masterOffset = Number(info.master_repl_offset);
+        // A partial sync resumes at the byte after the replica's offset
+        const backlogStart = info.repl_backlog_active === '1' ? Number(info.repl_backlog_first_byte_offset) : NaN;
+        const beyond = (l) => !isNaN(backlogStart) && l.offset + 1 < backlogStart;
+        linksEl.innerHTML = links.length ? `
+          <table class="history-table">
+            <thead><tr><th>replica</th><th>state</th><th>offset</th><th>behind</th><th>lag (s)</th></tr></thead>
+            <tbody>${links.map((l) => `
+              <tr>
+                <td>${escapeHtml(l.ip)}:${escapeHtml(l.port)}</td>
+                <td class="${l.state === 'online' ? '' : 'flag-bad'}">${escapeHtml(l.state)}</td>
+                <td class="num">${fmtI(l.offset)}</td>
+                <td class="num ${beyond(l) ? 'flag-bad' : ''}" title="${beyond(l) ? 'beyond the backlog, a reconnect needs a full sync' : ''}">${isNaN(masterOffset) ? '' : formatBytes(Math.max(0, masterOffset - l.offset))}${beyond(l) ? ' ⚠' : ''}</td>
+                <td class="num ${l.lag > 1 ? 'flag-bad' : ''}">${fmtI(l.lag)}</td>
+              </tr>`).join('')}
+            </tbody>
+          </table>` : '';
+        if (!isUnreachable(item)) {
+          const backlog = info.repl_backlog_active === '1'
+            ? `backlog ${formatBytes(Number(info.repl_backlog_histlen))} of ${formatBytes(Number(info.repl_backlog_size))}`
+            : 'no backlog';
+          const err = Number(info.sync_partial_err) > 0 ? 'flag-bad' : '';
+          linksEl.innerHTML += `<div class="muted repl-summary">${backlog}; syncs served: ${fmtI(info.sync_full)} full,
+            ${fmtI(info.sync_partial_ok)} partial, <span class="${err}">${fmtI(info.sync_partial_err)} partial refused</span></div>`;
+        }
+      }
 
       // Refresh charts
       if (s.charts) {
         s.charts.commands.update();
         s.charts.cpu.update();
         s.charts.mem.update();
+        if (s.charts.keys.data.datasets.length !== Object.keys(s.keys).length) s.charts.keys.data.datasets = keyDatasets(s);
+        s.charts.keys.update();
+        s.charts.memDetail.update();
       }
     }
 
+    // Each Sentinel's health and its view of the masters it monitors
+    function renderSentinels(sentinels) {
+      const panel = byId('sentinel-panel');
+      if (!sentinels || !sentinels.length) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      byId('sentinel-grid').innerHTML = sentinels.map((st) => {
+        const rows = (st.masters || []).map((m) => {
+          const down = /s_down|o_down/.test(m.flags || '');
+          return `
+            <tr>
+              <td>${escapeHtml(m.name)}</td>
+              <td>${escapeHtml(m.ip)}:${escapeHtml(m.port)}</td>
+              <td class="${down ? 'flag-bad' : ''}">${escapeHtml(m.flags)}</td>
+              <td class="num">${escapeHtml(m.quorum)}</td>
+              <td class="num">${escapeHtml(m.num_other_sentinels)}</td>
+              <td class="${m.quorum_ok ? 'flag-ok' : 'flag-bad'}" title="${escapeHtml(m.quorum_message)}">${m.quorum_ok ? 'OK' : 'NO QUORUM'}</td>
+            </tr>`;
+        }).join('');
+        const table = rows ? `
+          <table class="instance-info-table">
+            <thead><tr><th>master</th><th>address</th><th>flags</th><th>quorum</th><th>other sentinels</th><th>ckquorum</th></tr></thead>
+            <tbody>${rows}</tbody>
+          </table>` : '';
+        const origin = st.configured ? '' : ' <span class="muted">(discovered)</span>';
+        return `
+          <div class="replica-card sentinel-card${isUnreachable(st) ? ' down' : ''}">
+            <div class="replica-title">Sentinel ${escapeHtml(st.ip)}:${escapeHtml(st.port)}${origin}${statusHtml(st)}</div>
+            ${table}
+          </div>`;
+      }).join('');
+    }
+
+    // Firing alerts first, then the ones waiting for their `for` duration
+    function renderAlerts(alerts) {
+      const panel = byId('alerts-panel');
+      if (!alerts || !alerts.length) { panel.style.display = 'none'; panel.innerHTML = ''; return; }
+      panel.style.display = '';
+      panel.innerHTML = alerts.map((a) => {
+        const since = new Date(a.fired_at || a.since).toLocaleTimeString();
+        return `
+          <div class="alert-item ${a.state}">
+            <strong>${escapeHtml(a.state.toUpperCase())}</strong>
+            <span class="muted">${escapeHtml(a.severity)}</span>
+            <strong style="margin-left:.5rem;">${escapeHtml(a.rule)}</strong> on ${escapeHtml(a.node)}
+            <span class="muted">${escapeHtml(a.when)} (value ${escapeHtml(a.value)}) since ${since}</span>
+          </div>`;
+      }).join('');
+    }
+
+    // CLUSTER INFO of the seed; red while the cluster reports `fail`
+    function renderClusterInfo(ci) {
+      const banner = byId('cluster-banner');
+      if (!ci) { banner.style.display = 'none'; return; }
+      banner.style.display = '';
+      const ok = ci.state === 'ok';
+      banner.className = `cluster-banner ${ok ? 'ok' : 'fail'}`;
+      const bad = (n) => n > 0 ? ' class="flag-bad"' : '';
+      banner.innerHTML = `
+        <strong>Cluster state ${escapeHtml(String(ci.state).toUpperCase())}</strong>
+        <span class="muted">slots assigned ${ci.slots_assigned} / ok ${ci.slots_ok} /
+          <span${bad(ci.slots_pfail)}>pfail ${ci.slots_pfail}</span> /
+          <span${bad(ci.slots_fail)}>fail ${ci.slots_fail}</span></span>
+        <span class="muted">known nodes ${ci.known_nodes}</span>
+        <span class="muted">size ${ci.size}</span>
+        <span class="muted">epoch ${ci.current_epoch}</span>
+        <span class="muted">via ${escapeHtml(ci.seed)}</span>`;
+    }
+
+    // Disagreements between the CLUSTER NODES views of the masters (opt-in per cluster)
+    function renderConsistency(c) {
+      const panel = byId('consistency-panel');
+      if (!c) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      byId('consistency-summary').innerHTML = c.consistent
+        ? `<span class="flag-ok">consistent across ${c.views.length} views</span>`
+        : `<span class="flag-bad">${c.disagreements.length} disagreements across ${c.views.length} views</span>`;
+      byId('consistency-list').innerHTML = c.disagreements.map((d) =>
+        `<li><span class="consistency-kind">${escapeHtml(d.kind)}</span>${escapeHtml(d.message)}</li>`
+      ).join('');
+    }
+
+    // 16384-slot coverage bar: one colour per master, red for uncovered slots, yellow for migrations
+    const SLOT_COUNT = 16384;
+    const SLOT_COLORS = ['#0074d9', '#2ecc40', '#b10dc9', '#ff851b', '#39cccc', '#85144b', '#3d9970', '#001f3f'];
+    const fmtRanges = (ranges) => ranges.map(([a, b]) => a === b ? `${a}` : `${a}-${b}`).join(', ');
+    function renderSlots(slots) {
+      const panel = byId('slots-panel');
+      if (!slots) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      const canvas = byId('slots-bar');
+      const ctx = canvas.getContext('2d');
+      const w = canvas.width, h = canvas.height;
+      const span = (start, end, color, height) => {
+        ctx.fillStyle = color;
+        ctx.fillRect(start * w / SLOT_COUNT, 0, Math.max(1, (end - start + 1) * w / SLOT_COUNT), height);
+      };
+      ctx.clearRect(0, 0, w, h);
+      slots.uncovered.forEach(([a, b]) => span(a, b, '#ff4136', h));
+      const addrById = {};
+      slots.owners.forEach((o, i) => {
+        addrById[o.id] = o.addr;
+        o.ranges.forEach(([a, b]) => span(a, b, SLOT_COLORS[i % SLOT_COLORS.length], h));
+      });
+      slots.migrations.forEach((m) => span(m.slot, m.slot, '#ffdc00', h));
+
+      byId('slots-summary').textContent = `${slots.covered} / ${SLOT_COUNT} covered`;
+      byId('slots-legend').innerHTML = slots.owners.map((o, i) => `
+        <span title="${escapeHtml(fmtRanges(o.ranges))}"><span class="slots-swatch" style="background:${SLOT_COLORS[i % SLOT_COLORS.length]}"></span>${escapeHtml(o.addr)} <span class="muted">${o.count} slots</span></span>`
+      ).join('');
+      const missing = slots.uncovered.reduce((n, [a, b]) => n + b - a + 1, 0);
+      byId('slots-uncovered').textContent = missing ? `${missing} slots not covered: ${fmtRanges(slots.uncovered)}` : '';
+      const peer = (id) => addrById[id] || id.slice(0, 8);
+      byId('slots-migrations').innerHTML = slots.migrations.length ? `
+        <table class="history-table">
+          <thead><tr><th>slot</th><th>from</th><th>to</th><th>state</th></tr></thead>
+          <tbody>${slots.migrations.map((m) => `
+            <tr>
+              <td class="num">${m.slot}</td>
+              <td>${escapeHtml(peer(m.from))}</td>
+              <td>${escapeHtml(peer(m.to))}</td>
+              <td>${[m.migrating ? 'MIGRATING' : '', m.importing ? 'IMPORTING' : ''].filter(Boolean).join(' / ')}</td>
+            </tr>`).join('')}
+          </tbody>
+        </table>` : '';
+    }
+
     // SSE: render masters; attach each slave next to its master using master_host/master_port
-    const evtSource = new EventSource(`/${CLUSTER}/events`);
-    evtSource.onmessage = (event) => {
-      let list;
-      try { list = JSON.parse(event.data); }
+    // LATENCY LATEST of every node, and the spikes logged by the server
+    let latencySpikes = [];
+    function renderLatencyEvents(list, spikes) {
+      if (spikes && spikes.length) latencySpikes = latencySpikes.concat(spikes).slice(-MAX_EVENTS);
+      const rows = [];
+      list.forEach((item) => (item.latency || []).forEach((l) => rows.push({ node: `${item.ip}:${item.port}`, ...l })));
+      const panel = byId('latency-panel');
+      if (!rows.length && !latencySpikes.length) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      rows.sort((a, b) => b.ts - a.ts);
+      byId('latency-tbody').innerHTML = rows.length ? rows.map((l) => `
+        <tr>
+          <td>${escapeHtml(l.node)}</td>
+          <td>${escapeHtml(l.event)}</td>
+          <td>${new Date(l.ts).toLocaleString()}</td>
+          <td class="num">${fmtI(l.latest_ms)}</td>
+          <td class="num">${fmtI(l.max_ms)}</td>
+        </tr>`).join('') : '<tr><td colspan="5" class="muted">No latency events</td></tr>';
+      byId('latency-spikes-tbody').innerHTML = latencySpikes.slice(-20).reverse().map((l) => `
+        <tr>
+          <td>${new Date(l.ts).toLocaleString()}</td>
+          <td>${escapeHtml(l.node)}</td>
+          <td>${escapeHtml(l.event)}</td>
+          <td class="num">${fmtI(l.ms)}</td>
+        </tr>`).join('');
+    }
+
+    // Slow queries of every node, newest first, filtered by node and command
+    let slowQueries = [];
+    const MAX_SLOW_QUERIES = 1000;
+    const SLOW_QUERY_ROWS = 100;
+
+    function setOptions(select, values) {
+      const key = values.join(' ');
+      if (select.dataset.values === key) return;
+      select.dataset.values = key;
+      const current = select.value;
+      select.innerHTML = '<option value="">all</option>' + values.map((v) => `<option value="${escapeHtml(v)}">${escapeHtml(v)}</option>`).join('');
+      select.value = values.includes(current) ? current : '';
+    }
+
+    function renderSlowQueries(queries) {
+      if (queries && queries.length) slowQueries = slowQueries.concat(queries).slice(-MAX_SLOW_QUERIES);
+      const panel = byId('slowlog-panel');
+      if (!slowQueries.length) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      const nodeSel = byId('slowlog-node');
+      const cmdSel = byId('slowlog-command');
+      setOptions(nodeSel, [...new Set(slowQueries.map((q) => q.node))].sort());
+      setOptions(cmdSel, [...new Set(slowQueries.map((q) => q.command.toLowerCase()))].sort());
+      const matching = slowQueries.filter((q) =>
+        (!nodeSel.value || q.node === nodeSel.value) && (!cmdSel.value || q.command.toLowerCase() === cmdSel.value));
+      byId('slowlog-summary').textContent = `${matching.length} of ${slowQueries.length}`;
+      byId('slowlog-tbody').innerHTML = matching.slice(-SLOW_QUERY_ROWS).reverse().map((q) => `
+        <tr>
+          <td>${new Date(q.ts).toLocaleString()}</td>
+          <td>${escapeHtml(q.node)}</td>
+          <td class="num">${fmtF2(q.duration_us / 1000)}</td>
+          <td class="slowlog-args">${escapeHtml([q.command, ...q.args].join(' '))}</td>
+          <td>${escapeHtml(q.client_addr)}${q.client_name ? ` <span class="muted">${escapeHtml(q.client_name)}</span>` : ''}</td>
+        </tr>`).join('');
+    }
+    byId('slowlog-node').addEventListener('change', () => renderSlowQueries());
+    byId('slowlog-command').addEventListener('change', () => renderSlowQueries());
+
+    // Latest CLIENT LIST aggregates per node, sampled by the server at its own interval
+    const clientSummaries = {};
+    let clientsDrill = null;
+
+    function clientGroupTable(title, by, groups) {
+      const rows = groups.map((g) => {
+        const drill = g.key.startsWith('(') ? '' : ` data-by="${by}" data-key="${escapeHtml(g.key)}"`;
+        return `
+          <tr${drill}>
+            <td>${g.key ? escapeHtml(g.key) : '<span class="muted">(none)</span>'}</td>
+            <td class="num">${g.count}</td>
+            <td class="num">${formatBytes(g.qbuf + g.omem)}</td>
+            <td class="num">${fmtI(g.max_idle)}</td>
+          </tr>`;
+      }).join('');
+      return `<div><h4>${title}</h4><table class="history-table">
+        <thead><tr><th>${by}</th><th>clients</th><th>buffers</th><th>max idle (s)</th></tr></thead>
+        <tbody>${rows}</tbody></table></div>`;
+    }
+
+    function clientRows(clients) {
+      return clients.map(({ node, client: c }) => `
+        <tr>
+          ${node ? `<td>${escapeHtml(node)}</td>` : ''}
+          <td>${escapeHtml(c.addr)}</td>
+          <td>${escapeHtml(c.name)}</td>
+          <td>${escapeHtml([c.lib_name, c.lib_ver].filter(Boolean).join(' '))}</td>
+          <td>${escapeHtml(c.flags)}</td>
+          <td class="num">${fmtI(c.age)}</td>
+          <td class="num">${fmtI(c.idle)}</td>
+          <td class="num">${formatBytes(c.qbuf)}</td>
+          <td class="num">${formatBytes(c.omem)}</td>
+          <td>${escapeHtml(c.cmd)}</td>
+        </tr>`).join('');
+    }
+    const CLIENT_HEAD = '<th>addr</th><th>name</th><th>lib</th><th>flags</th><th>age (s)</th><th>idle (s)</th><th>qbuf</th><th>omem</th><th>last cmd</th>';
+
+    function renderClients() {
+      const nodes = Object.keys(clientSummaries).sort();
+      const panel = byId('clients-panel');
+      if (!nodes.length) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      const select = byId('clients-node');
+      if (select.dataset.values !== nodes.join(' ')) {
+        const current = select.value;
+        select.dataset.values = nodes.join(' ');
+        select.innerHTML = nodes.map((n) => `<option value="${escapeHtml(n)}">${escapeHtml(n)}</option>`).join('');
+        select.value = nodes.includes(current) ? current : nodes[0];
+      }
+      const sum = clientSummaries[select.value];
+      byId('clients-summary').textContent = `${sum.total} on ${select.value}, sampled at ${new Date(sum.ts).toLocaleTimeString()}`;
+      const peak = Math.max(1, ...sum.idle.map(([, n]) => n));
+      const listed = (title, by, count, clients) => `
+        <div><h4${count ? ` data-by="${by}" class="muted"` : ''}>${title}: ${count}</h4>
+        ${clients.length ? `<table class="history-table"><thead><tr>${CLIENT_HEAD}</tr></thead>
+          <tbody>${clientRows(clients.slice(0, 10).map((client) => ({ client })))}</tbody></table>` : ''}</div>`;
+      byId('clients-body').innerHTML = `
+        <div class="clients-grid">
+          ${clientGroupTable('By host', 'host', sum.by_host)}
+          ${clientGroupTable('By name', 'name', sum.by_name)}
+          ${clientGroupTable('By library', 'lib', sum.by_lib)}
+          <div>
+            <h4>Idle time</h4>
+            <div class="clients-idle">${sum.idle.map(([label, n]) => `<div title="${label}: ${n}" style="height:${Math.round(n / peak * 100)}%"></div>`).join('')}</div>
+            <div class="clients-idle-labels">${sum.idle.map(([label, n]) => `<span data-by="idle" data-key="${escapeHtml(label)}" style="cursor:pointer">${escapeHtml(label)}<br>${n}</span>`).join('')}</div>
+          </div>
+        </div>
+        <h4>Largest buffers</h4>
+        ${sum.largest_buffers.length ? `<table class="history-table"><thead><tr>${CLIENT_HEAD}</tr></thead>
+          <tbody>${clientRows(sum.largest_buffers.map((client) => ({ client })))}</tbody></table>` : '<div class="muted">none</div>'}
+        <div class="clients-grid">
+          ${listed('Blocked', 'blocked', sum.blocked_count, sum.blocked)}
+          ${listed('Pub/Sub', 'pubsub', sum.pubsub_count, sum.pubsub)}
+        </div>`;
+    }
+
+    function drillClients(by, key) {
+      const node = byId('clients-node').value;
+      clientsDrill = { by, key };
+      const params = new URLSearchParams({ node, by, key });
+      fetch(`/${CLUSTER}/clients?${params}`)
+        .then(res => res.json())
+        .then((data) => {
+          if (clientsDrill.by !== by || clientsDrill.key !== key) return;
+          const clients = data.clients || [];
+          byId('clients-drill').innerHTML = `
+            <h4>${escapeHtml(by)}${key ? ` = ${escapeHtml(key)}` : ''}: ${clients.length} clients <button id="clients-drill-close">close</button></h4>
+            <table class="history-table"><thead><tr><th>node</th>${CLIENT_HEAD}</tr></thead>
+              <tbody>${clientRows(clients)}</tbody></table>`;
+          byId('clients-drill-close').addEventListener('click', () => {
+            clientsDrill = null;
+            byId('clients-drill').innerHTML = '';
+          });
+        })
+        .catch(() => {});
+    }
+
+    byId('clients-node').addEventListener('change', () => {
+      byId('clients-drill').innerHTML = '';
+      renderClients();
+    });
+    byId('clients-body').addEventListener('click', (e) => {
+      const target = e.target.closest('[data-by]');
+      if (target) drillClients(target.dataset.by, target.dataset.key || '');
+    });
+
+    // Total keys of every master over time, on a shared time axis
+    let keysChart = null;
+    function renderKeysPerMaster() {
+      const masters = Object.values(mastersState).filter((s) => s.keysTotal.some((n) => n != null));
+      const panel = byId('keys-panel');
+      if (!masters.length) { panel.style.display = 'none'; return; }
+      panel.style.display = '';
+      const datasets = masters.map((s, i) => {
+        const color = KEY_COLORS[i % KEY_COLORS.length];
+        return {
+          label: [...s.addrs][0],
+          data: s.ts.map((x, j) => ({ x, y: s.keysTotal[j] })),
+          borderColor: color, backgroundColor: color + '2e', fill: false, tension: 0.35, pointRadius: 0, spanGaps: false
+        };
+      });
+      if (!keysChart) {
+        keysChart = new Chart(byId('keys-total-chart').getContext('2d'), {
+          type: 'line',
+          data: { datasets },
+          options: {
+            responsive: true, maintainAspectRatio: false, animation: false,
+            scales: {
+              x: { type: 'linear', ticks: { callback: (v) => new Date(v).toLocaleTimeString() } },
+              y: { beginAtZero: true }
+            }
+          }
+        });
+      } else {
+        keysChart.data.datasets = datasets;
+        keysChart.update();
+      }
+    }
+
+    function onSnapshot(event) {
+      let snapshot;
+      try { snapshot = JSON.parse(event.data); }
       catch { byId('masters-list').innerHTML = '<div class="muted">Data parse error</div>'; return; }
+      const list = snapshot && snapshot.nodes;
       if (!Array.isArray(list)) { byId('masters-list').innerHTML = '<div class="muted">No instances</div>'; return; }
+      renderAlerts(snapshot.alerts);
+      renderSentinels(snapshot.sentinels);
+      renderSlots(snapshot.slots);
+      addEvents(snapshot.events);
+      renderClusterInfo(snapshot.cluster_info);
+      renderConsistency(snapshot.consistency);
+      renderLatencyEvents(list, snapshot.latency_spikes);
+      renderSlowQueries(snapshot.slow_queries);
+      list.forEach((item) => { if (item.clients) clientSummaries[`${item.ip}:${item.port}`] = item.clients; });
+      Object.keys(clientSummaries).forEach((node) => {
+        if (!list.some((item) => `${item.ip}:${item.port}` === node)) delete clientSummaries[node];
+      });
+      renderClients();
 
-      // Build a map from master addr -> idx (in this payload)
+      // Build a map from master addr -> idx; the idx stays with the address across
+      // snapshots, so nodes appearing or disappearing do not mix two masters' series
       const masterIndexByAddr = {};
-      list.forEach((item, idx) => {
+      list.forEach((item) => {
         const info = item.info || item || {};
         const role = (info.role || item.role || '').toLowerCase();
         if (role === 'master') {
+          const addr = `${item.ip}:${item.port}`;
+          if (masterKeys[addr] == null) masterKeys[addr] = nextMasterKey++;
+          const idx = masterKeys[addr];
+          masterIndexByAddr[addr] = idx;
           const ip = info.ip || item.ip || '';
           // CHANGED: fallback to port OR tcp_port
           const port = info.tcp_port ?? info.port ?? item.tcp_port ?? item.port ?? '';
@@ -402,21 +1446,40 @@ pub const CLUSTER_HTML: &str = r#"
         }
       });
 
+      // Masters that left, e.g. demoted by a failover, are dropped with their charts
+      Object.entries(masterKeys).forEach(([addr, idx]) => {
+        if (masterIndexByAddr[addr] != null) return;
+        const s = mastersState[idx];
+        if (s && s.charts) Object.values(s.charts).forEach((chart) => chart.destroy());
+        const block = byId(`master-block-${idx}`);
+        if (block) block.remove();
+        delete mastersState[idx];
+        delete masterKeys[addr];
+      });
+
       // Update: pass ip, port to updateMaster so we can display correct values
       // First pass: render/update masters and ensure DOM
-      list.forEach((item, idx) => {
+      list.forEach((item) => {
         const info = item.info || item || {};
         const role = (info.role || item.role || '').toLowerCase();
         if (role !== 'master') return;
+        const idx = masterKeys[`${item.ip}:${item.port}`];
         const ip = info.ip || item.ip || '';
         // fallback to tcp_port or port
         const port = info.tcp_port ?? info.port ?? item.tcp_port ?? item.port ?? '';
         if (!mastersState[idx]) {
-          mastersState[idx] = { labels: [], cmd: [], cpuSys: [], cpuUsr: [], mem: [], memRss: [], prev: null, prevTime: 0, history: [], charts: null };
+          mastersState[idx] = newMasterState(`${item.ip}:${item.port}`);
           ensureMasterDOM(idx, ip, port, info);
         }
+        mastersState[idx].addrs = new Set([`${item.ip}:${item.port}`]);
         // CHANGED: pass ip, port here
-        updateMaster(idx, info, ip, port);
+        updateMaster(idx, info, ip, port, item);
+      });
+
+      // Nodes whose role could not be determined (e.g. unreachable seeds)
+      const unknown = list.filter((item) => {
+        const role = ((item.info || {}).role || item.role || '').toLowerCase();
+        return !(role === 'master' || role === 'slave' || role === 'replica');
       });
 
       // Clear replicas column for all masters in this payload
@@ -426,7 +1489,7 @@ pub const CLUSTER_HTML: &str = r#"
       });
 
       // Second pass: attach slaves to their master block
-      list.forEach((item, idx) => {
+      list.forEach((item) => {
         const info = item.info || item || {};
         const role = (info.role || item.role || '').toLowerCase();
         if (!(role === 'slave' || role === 'replica')) return;
@@ -436,19 +1499,61 @@ pub const CLUSTER_HTML: &str = r#"
         const port = info.tcp_port ?? info.port ?? item.tcp_port ?? item.port ?? '';
         const mh = info.master_host || item.master_host || '';
         const mp = info.master_port || item.master_port || '';
-        const masterIdx = masterIndexByAddr[`${mh}:${mp}`];
+        // prefer the master the collector discovered this replica from
+        const masterIdx = masterIndexByAddr[item.master || `${mh}:${mp}`] ?? masterIndexByAddr[`${mh}:${mp}`];
 
         if (masterIdx != null) {
           // stable key per slave under its master
           const repKey = `${ip}-${port}`.replace(/[^a-zA-Z0-9_-]/g,'_');
-          ensureReplicaCard(masterIdx, repKey, ip, port, info);
+          ensureReplicaCard(masterIdx, repKey, ip, port, info, item);
+          mastersState[masterIdx].addrs.add(`${item.ip}:${item.port}`);
+          pushReplica(mastersState[masterIdx], `${item.ip}:${item.port}`, item.sample);
+        } else {
+          // e.g. a cluster replica whose master is not in the node table
+          unknown.push(item);
         }
       });
-    };
-    evtSource.onerror = () => {
-      const masters = byId('masters-list');
-      if (masters && !masters.innerHTML) masters.innerHTML = '<div class="muted">SSE connection error</div>';
-    };
+
+      Object.values(masterIndexByAddr).forEach(renderReplication);
+      renderKeysPerMaster();
+
+      byId('unreachable-list').innerHTML = unknown.map((item) => {
+        const role = item.role ? `${escapeHtml(item.role)} ` : '';
+        return `<div class="replica-card${isUnreachable(item) ? ' down' : ''}"><div class="replica-title">Node ${role}${escapeHtml(item.ip)}:${escapeHtml(item.port)}${statusHtml(item)}${clusterHtml(item)}</div></div>`;
+      }).join('');
+    }
+
+    // Backfill the charts and the event log from the server, then follow live snapshots
+    Promise.all([
+      fetch(`/${CLUSTER}/history`)
+        .then(res => res.json())
+        .then(data => { backfill = data.nodes || {}; })
+        .catch(() => {}),
+      fetch(`/${CLUSTER}/events.json`)
+        .then(res => res.json())
+        .then(data => addEvents(data.events))
+        .catch(() => {}),
+      fetch(`/${CLUSTER}/clients`)
+        .then(res => res.json())
+        .then(data => Object.assign(clientSummaries, data.nodes || {}))
+        .catch(() => {}),
+      fetch(`/${CLUSTER}/slowlog`)
+        .then(res => res.json())
+        .then(data => renderSlowQueries(data.queries))
+        .catch(() => {}),
+      fetch(`/${CLUSTER}/latency`)
+        .then(res => res.json())
+        .then(data => { latencySpikes = (data.spikes || []).slice(-MAX_EVENTS); })
+        .catch(() => {}),
+    ])
+      .finally(() => {
+        const evtSource = new EventSource(`/${CLUSTER}/events`);
+        evtSource.onmessage = onSnapshot;
+        evtSource.onerror = () => {
+          const masters = byId('masters-list');
+          if (masters && !masters.innerHTML) masters.innerHTML = '<div class="muted">SSE connection error</div>';
+        };
+      });
   </script>
 </body>
 </html>
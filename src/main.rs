@@ -1,7 +1,8 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
     Json,
-    response::{sse::{Event, Sse}, Html},
+    response::{sse::{Event, Sse}, Html, IntoResponse},
     routing::get,
     Router,
 };
@@ -10,14 +11,64 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
-use tokio_stream::{wrappers::IntervalStream, StreamExt};
-use redis::{Client, Connection};
+use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 use mellow::{INDEX_HTML, CLUSTER_HTML};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+mod addr;
+mod alerts;
+mod clients;
+mod cluster;
+mod collector;
+mod commandstats;
+mod consistency;
+mod error;
+mod events;
+mod history;
+mod latency;
+mod metrics;
+mod nodelog;
+mod persistence;
+mod pool;
+mod replication;
+mod reply;
+mod rules;
+mod sentinel;
+mod slowlog;
+use alerts::{Alert, Alerting};
+use clients::ClientFilter;
+use collector::Collector;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 struct RedisInstance {
     ip: String,
     port: String,
+    // Per-node overrides of the cluster timeouts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connect_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum ClusterMode {
+    // Standalone or Redis Cluster, detected from the seed's INFO
+    #[default]
+    Auto,
+    // `instances` are Sentinels
+    Sentinel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SlowlogRedaction {
+    // Every argument as logged by Redis
+    None,
+    // The command and its first argument, usually the key
+    #[default]
+    Values,
+    // The command name only
+    All,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,210 +76,206 @@ struct RedisCluster {
     name: String,
     instances: Vec<RedisInstance>,
     password: String,
+    #[serde(default)]
+    mode: ClusterMode,
+    // Defaults to `password` when the Sentinels share it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sentinel_password: Option<String>,
+    // Compare the CLUSTER NODES view of every master on each tick
+    #[serde(default)]
+    check_consistency: bool,
+    // What is kept of the arguments of slow queries
+    #[serde(default)]
+    slowlog_redact: SlowlogRedaction,
+    // How often CLIENT LIST is sampled on every node; 0 disables it
+    #[serde(default = "default_client_list_interval_secs")]
+    client_list_interval_secs: u64,
+    // How often MEMORY STATS and MEMORY DOCTOR are read on every node; 0 disables them
+    #[serde(default = "default_memory_interval_secs")]
+    memory_interval_secs: u64,
+    // Warn about unsaved changes once the last RDB save is older than this; 0 disables it
+    #[serde(default)]
+    max_save_age_secs: u64,
+    // Per-cluster overrides of the global timeouts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connect_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command_timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-struct RedisConfig {
-    clusters: Vec<RedisCluster>,
+struct AlertRule {
+    name: String,
+    // e.g. "used_memory / maxmemory > 0.9 for 60s", evaluated on every node
+    when: String,
+    #[serde(default = "default_severity")]
+    severity: String,
+    // Names of the clusters the rule applies to; all of them when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clusters: Option<Vec<String>>,
 }
 
-type AppState = Arc<RedisConfig>;
-
-fn setup_redis_client(cluster: &RedisCluster) -> Connection {
-    for instance in &cluster.instances {
-        let uri: String = if cluster.password.is_empty() {
-            format!("redis://{}:{}", instance.ip, instance.port)
-        } else {
-            format!("redis://default:{}@{}:{}", cluster.password, instance.ip, instance.port)
-        };
-        if let Ok(client) = Client::open(uri) {
-            if let Ok(con) = client.get_connection() {
-                return con;
-            }
-        }
-    }
-    panic!("Failed to connect to any Redis instance in the cluster {}", cluster.name);
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Webhook {
+    url: String,
+    // Sent with every request, e.g. an Authorization header
+    #[serde(default)]
+    headers: HashMap<String, String>,
 }
 
-fn get_redis_info(con: &mut Connection) -> String {
-    let info: String = redis::cmd("INFO").query(con)
-        .expect("Failed to get Redis info");
-    info
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct RedisConfig {
+    clusters: Vec<RedisCluster>,
+    #[serde(default)]
+    alerts: Vec<AlertRule>,
+    #[serde(default)]
+    webhooks: Vec<Webhook>,
+    #[serde(default = "default_timeout_ms")]
+    connect_timeout_ms: u64,
+    #[serde(default = "default_timeout_ms")]
+    command_timeout_ms: u64,
 }
 
-fn parse_redis_info(info: &str) -> HashMap<String, String> {
-    let mut map: HashMap<String, String> = HashMap::new();
-    for line in info.lines() {
-        if let Some((key, value)) = line.split_once(':') {
-            map.insert(key.to_string(), value.to_string());
-        }
-    }
-    map
+fn default_timeout_ms() -> u64 {
+    1000
 }
 
-fn get_replicas(info_map: &HashMap<String, String>) -> Vec<(String, String)> {
-    let mut replicas: Vec<(String, String)> = Vec::new();
-    for i in 0..5 {
-        if let Some(replica) = info_map.get(&format!("slave{}", i)) {
-            // slave$i=ip, port, state, offset, lag
-            let mut ip = None;
-            let mut port = None;
-            for item in replica.split(',') {
-                if let Some((k, v)) = item.split_once('=') {
-                    match k {
-                        "ip" => ip = Some(v.to_string()),
-                        "port" => port = Some(v.to_string()),
-                        _ => {}
-                    }
-                }
-            }
-            if let (Some(ip), Some(port)) = (ip, port) {
-                replicas.push((ip, port));
-            }
-        }
-    }
-    replicas
+fn default_client_list_interval_secs() -> u64 {
+    10
 }
 
-fn get_cluster_masters(con: &mut Connection) -> Vec<(String, String)> {
-    let mut masters: Vec<(String, String)> = Vec::new();
-    let nodes: String = redis::cmd("CLUSTER")
-        .arg("NODES")
-        .query(con)
-        .expect("Failed to get cluster nodes");
-    // id ip:port@cport master - ping-sent pong-recv config-epoch link-state slots
-    nodes.lines().filter_map(|line| {
-        let items: Vec<&str> = line.split_whitespace().collect();
-        if items.len() > 2 && items[2].contains("master") {
-            let addr: Vec<&str> = items[1].split('@').collect();
-            if addr.len() == 2 {
-                let ip_port: Vec<&str> = addr[0].split(':').collect();
-                if ip_port.len() == 2 {
-                    return Some((ip_port[0].to_string(), ip_port[1].to_string()));
-                }
-            }
-        }
-        None
-    }).for_each(|(ip, port)| {
-        masters.push((ip, port));
-    });
-    masters
+fn default_memory_interval_secs() -> u64 {
+    10
 }
 
-fn generics_handler<F, R>(
-    name: String,
-    config: &RedisConfig,
-    mut node_callback: F,
-) -> R
-where
-    F: FnMut(&RedisCluster, &HashMap<String, String>, &str) -> R,
-    R: Default + Clone,
-{
-    let cluster = config.clusters.iter()
-        .find(|c| c.name == name)
-        .expect(&format!("Cluster {} not found", name))
-        .clone();
-
-    let mut con: Connection = setup_redis_client(&cluster);
-    let info: String = get_redis_info(&mut con);
-    let info_map: HashMap<String, String> = parse_redis_info(&info);
-
-    let cluster_enabled: bool = info_map.get("cluster_enabled")
-        .map(|v| v == "1").unwrap_or(false);
-
-    let mut result = R::default();
-
-    if cluster_enabled {
-        for (ip, port) in get_cluster_masters(&mut con) {
-            let master_cluster = RedisCluster {
-                name: cluster.name.clone(),
-                instances: vec![RedisInstance { ip: ip.clone(), port: port.clone() }],
-                password: cluster.password.clone(),
-            };
-            if let Ok(mut master_con) = std::panic::catch_unwind(|| setup_redis_client(&master_cluster)) {
-                let master_info: String = get_redis_info(&mut master_con);
-                let master_info_map: HashMap<String, String> = parse_redis_info(&master_info);
-                result = node_callback(&master_cluster, &master_info_map, &ip);
-                collect_replica_info_callback(&master_cluster, &master_info_map, &mut node_callback);
-            }
-        }
-    } else {
-        let role: &str = info_map.get("role").map(|v| v.as_str()).unwrap();
-        if role == "master" {
-            let ip: String = cluster.instances[0].ip.clone();
-            result = node_callback(&cluster, &info_map, &ip);
-            collect_replica_info_callback(&cluster, &info_map, &mut node_callback);
-        } else if role == "slave" {
-            let master_ip: String = info_map.get("master_host")
-                .cloned().unwrap_or_default();
-            let master_port: String = info_map.get("master_port")
-                .cloned().unwrap_or_default();
-            if !master_ip.is_empty() && !master_port.is_empty() {
-                let master_cluster = RedisCluster {
-                    name: cluster.name.clone(),
-                    instances: vec![RedisInstance { ip: master_ip.clone(), port: master_port.clone() }],
-                    password: cluster.password.clone(),
-                };
-                if let Ok(mut master_con) = std::panic::catch_unwind(|| setup_redis_client(&master_cluster)) {
-                    let master_info: String = get_redis_info(&mut master_con);
-                    let master_info_map: HashMap<String, String> = parse_redis_info(&master_info);
-                    result = node_callback(&master_cluster, &master_info_map, &master_ip);
-                    collect_replica_info_callback(&master_cluster, &master_info_map, &mut node_callback);
-                }
-            }
-        }
-    }
-    result
+fn default_severity() -> String {
+    "warning".to_string()
 }
 
-fn collect_replica_info_callback<F, R>(
-    cluster: &RedisCluster,
-    info_map: &HashMap<String, String>,
-    node_callback: &mut F,
-) where
-    F: FnMut(&RedisCluster, &HashMap<String, String>, &str) -> R,
-    R: Default + Clone,
-{
-    if info_map.get("connected_slaves")
-        .and_then(|v| v.parse::<u32>().ok()) > Some(0) {
-        for (rip, rport) in get_replicas(info_map) {
-            let replica_cluster = RedisCluster {
-                name: cluster.name.clone(),
-                instances: vec![RedisInstance { ip: rip.clone(), port: rport.clone() }],
-                password: cluster.password.clone(),
-            };
-            if let Ok(mut replica_con) = std::panic::catch_unwind(|| setup_redis_client(&replica_cluster)) {
-                let replica_info: String = get_redis_info(&mut replica_con);
-                let replica_info_map: HashMap<String, String> = parse_redis_info(&replica_info);
-                node_callback(&replica_cluster, &replica_info_map, &rip);
-            }
-        }
-    }
+#[derive(Clone)]
+struct AppState {
+    config: Arc<RedisConfig>,
+    collectors: Arc<HashMap<String, Collector>>,
 }
 
 async fn sse_handler(
     Path(name): Path<String>,
-    State(config): State<AppState>
-) -> Sse<impl tokio_stream::Stream<Item = Result<Event, axum::Error>>> {
-    let config = config.clone();
-    let name = name.clone();
-    let stream = IntervalStream::new(tokio::time::interval(Duration::from_secs(1)))
-        .map(move |_| {
-            let mut data: Vec<HashMap<String, String>> = Vec::new();
-            generics_handler(
-                name.clone(),
-                &config,
-                |_, info_map, ip| {
-                    let mut node_info = info_map.clone();
-                    node_info.insert("ip".to_string(), ip.to_string());
-                    data.push(node_info);
-                    ()
-                },
-            );
-            Ok(Event::default().data(serde_json::to_string(&data)
+    State(state): State<AppState>
+) -> Result<Sse<impl tokio_stream::Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // Subscribers that fall behind skip the snapshots they missed.
+    let stream = BroadcastStream::new(collector.subscribe())
+        .filter_map(|snapshot| snapshot.ok())
+        .map(|snapshot| {
+            Ok(Event::default().data(serde_json::to_string(&*snapshot)
                 .expect("Failed to serialize data to JSON")))
         });
-    Sse::new(stream)
+    Ok(Sse::new(stream))
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    // unix milliseconds; only samples or events newer than this are returned
+    since: Option<u64>,
+}
+
+async fn history_handler(
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let nodes = collector.history().since(query.since.unwrap_or(0));
+    Ok(Json(json!({ "nodes": nodes })))
+}
+
+async fn events_json_handler(
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let events = collector.events().since(query.since.unwrap_or(0));
+    Ok(Json(json!({ "events": events })))
+}
+
+async fn latency_handler(
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let spikes = collector.latency().since(query.since.unwrap_or(0));
+    Ok(Json(json!({ "spikes": spikes })))
+}
+
+async fn slowlog_handler(
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let queries = collector.slowlog().since(query.since.unwrap_or(0));
+    Ok(Json(json!({ "queries": queries })))
+}
+
+#[derive(Deserialize)]
+struct ClientsQuery {
+    // `ip:port`; every sampled node when omitted
+    node: Option<String>,
+    // host, name, lib, idle, blocked or pubsub
+    by: Option<String>,
+    #[serde(default)]
+    key: String,
+}
+
+async fn clients_handler(
+    Path(name): Path<String>,
+    Query(query): Query<ClientsQuery>,
+    State(state): State<AppState>
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let Some(by) = query.by else {
+        return Ok(Json(json!({ "nodes": collector.clients().summaries() })));
+    };
+    let filter: ClientFilter = ClientFilter::parse(&by).ok_or(StatusCode::BAD_REQUEST)?;
+    let clients: Vec<serde_json::Value> = collector.clients().find(query.node.as_deref(), filter, &query.key)
+        .into_iter()
+        .map(|(node, client)| json!({ "node": node, "client": client }))
+        .collect();
+    Ok(Json(json!({ "clients": clients })))
+}
+
+async fn slots_handler(
+    Path(name): Path<String>,
+    State(state): State<AppState>
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let collector: &Collector = state.collectors.get(&name)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // `null` unless the cluster runs in Redis Cluster mode
+    Ok(Json(json!({ "slots": collector.latest().slots })))
+}
+
+async fn alerts_handler(
+    State(state): State<AppState>
+) -> Json<serde_json::Value> {
+    let mut names: Vec<&String> = state.collectors.keys().collect();
+    names.sort();
+    let alerts: Vec<Alert> = names.into_iter()
+        .flat_map(|name| state.collectors[name].alerts().active())
+        .collect();
+    Json(json!({ "alerts": alerts }))
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    let (content_type, body) = metrics::render();
+    ([(CONTENT_TYPE, content_type)], body)
 }
 
 async fn index_handler() -> Html<&'static str> {
@@ -236,9 +283,9 @@ async fn index_handler() -> Html<&'static str> {
 }
 
 async fn clusters_json_handler(
-    State(config): State<AppState>
+    State(state): State<AppState>
 ) -> Json<serde_json::Value> {
-    let mut names : Vec<String> = config.clusters.iter()
+    let mut names : Vec<String> = state.config.clusters.iter()
         .map(|c| c.name.clone())
         .collect();
     names.sort();
@@ -283,13 +330,35 @@ async fn main() {
     let redis_config: RedisConfig = serde_json::from_str(&config_data)
         .unwrap_or_else(|e| panic!("Failed to parse config file '{}': {}", config_path, e));
     let shared_config: Arc<RedisConfig> = Arc::new(redis_config);
+    let alerting: Arc<Alerting> = Arc::new(Alerting::from_config(&shared_config)
+        .unwrap_or_else(|e| panic!("Invalid config file '{}': {}", config_path, e)));
+
+    // One background poller per cluster, shared by every dashboard viewer.
+    let collectors: HashMap<String, Collector> = shared_config.clusters.iter()
+        .map(|c| {
+            let collector = Collector::spawn(c.clone(), &shared_config, alerting.clone(), Duration::from_secs(1));
+            (c.name.clone(), collector)
+        })
+        .collect();
+    let state = AppState {
+        config: shared_config,
+        collectors: Arc::new(collectors),
+    };
 
     let app: Router = Router::new()
         .route("/", get(index_handler))
         .route("/clusters.json", get(clusters_json_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/alerts", get(alerts_handler))
         .route("/:name", get(named_index_handler))
         .route("/:name/events", get(sse_handler))
-        .with_state(shared_config);
+        .route("/:name/history", get(history_handler))
+        .route("/:name/events.json", get(events_json_handler))
+        .route("/:name/latency", get(latency_handler))
+        .route("/:name/slowlog", get(slowlog_handler))
+        .route("/:name/clients", get(clients_handler))
+        .route("/:name/slots", get(slots_handler))
+        .with_state(state);
 
     let addr: SocketAddr = SocketAddr::from(([127, 0, 0, 1], 8080));
     println!("Listening on http://{}", addr);
@@ -0,0 +1,249 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    core::Collector, CounterVec, Encoder, GaugeVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use crate::collector::{NodeReport, NodeStatus};
+
+const LABELS: &[&str] = &["cluster", "ip", "port", "role"];
+
+// INFO field, metric name, help
+const GAUGE_FIELDS: &[(&str, &str, &str)] = &[
+    ("used_memory", "redis_memory_used_bytes", "Bytes allocated by Redis"),
+    ("used_memory_rss", "redis_memory_used_rss_bytes", "Resident set size of the Redis process"),
+    ("maxmemory", "redis_memory_max_bytes", "Configured maxmemory, 0 when unlimited"),
+    ("connected_clients", "redis_connected_clients", "Client connections, excluding replicas"),
+    ("blocked_clients", "redis_blocked_clients", "Clients pending on a blocking call"),
+    ("connected_slaves", "redis_connected_slaves", "Connected replicas"),
+    ("master_repl_offset", "redis_master_repl_offset", "Replication offset of the master stream"),
+    ("slave_repl_offset", "redis_slave_repl_offset", "Replication offset applied by a replica"),
+    ("master_last_io_seconds_ago", "redis_master_last_io_seconds", "Seconds since a replica last heard from its master"),
+    ("master_sync_in_progress", "redis_master_sync_in_progress", "Whether a replica is loading a full sync"),
+    ("repl_backlog_histlen", "redis_repl_backlog_history_bytes", "Bytes of replication stream held in the backlog"),
+    ("uptime_in_seconds", "redis_uptime_in_seconds", "Seconds since the server started"),
+];
+
+const COUNTER_FIELDS: &[(&str, &str, &str)] = &[
+    ("total_commands_processed", "redis_commands_processed_total", "Commands processed by the server"),
+    ("total_connections_received", "redis_connections_received_total", "Connections accepted by the server"),
+    ("rejected_connections", "redis_rejected_connections_total", "Connections rejected because of maxclients"),
+    ("keyspace_hits", "redis_keyspace_hits_total", "Successful key lookups"),
+    ("keyspace_misses", "redis_keyspace_misses_total", "Failed key lookups"),
+    ("evicted_keys", "redis_evicted_keys_total", "Keys evicted because of maxmemory"),
+    ("expired_keys", "redis_expired_keys_total", "Key expiration events"),
+    ("sync_full", "redis_sync_full_total", "Full resynchronizations served to replicas"),
+    ("sync_partial_ok", "redis_sync_partial_ok_total", "Partial resynchronizations accepted"),
+    ("sync_partial_err", "redis_sync_partial_err_total", "Partial resynchronizations refused"),
+    ("used_cpu_sys", "redis_cpu_sys_seconds_total", "System CPU consumed by the server"),
+    ("used_cpu_user", "redis_cpu_user_seconds_total", "User CPU consumed by the server"),
+];
+
+type LabelSet = [String; 4];
+
+// Label sets exported for one cluster on the previous tick
+#[derive(Default)]
+struct Exported {
+    all: HashSet<LabelSet>,
+    reachable: HashSet<LabelSet>,
+}
+
+// Every exported series, registered with one registry.
+struct Metrics {
+    up: IntGaugeVec,
+    gauges: Vec<(&'static str, GaugeVec)>,
+    counters: Vec<(&'static str, CounterVec)>,
+    replica_lag: GaugeVec,
+    exported: Mutex<HashMap<String, Exported>>,
+}
+
+fn register<C: Collector + Clone + 'static>(registry: &Registry, collector: C) -> C {
+    registry.register(Box::new(collector.clone())).unwrap();
+    collector
+}
+
+lazy_static! {
+    static ref METRICS: Metrics = Metrics::new(prometheus::default_registry());
+}
+
+impl Metrics {
+    fn new(registry: &Registry) -> Self {
+        let gauge = |name: &str, help: &str| register(registry, GaugeVec::new(Opts::new(name, help), LABELS).unwrap());
+        Metrics {
+            up: register(registry, IntGaugeVec::new(Opts::new("redis_up", "Whether the last poll of the node succeeded"), LABELS).unwrap()),
+            gauges: GAUGE_FIELDS.iter().map(|(field, name, help)| (*field, gauge(name, help))).collect(),
+            counters: COUNTER_FIELDS.iter()
+                .map(|(field, name, help)| (*field, register(registry, CounterVec::new(Opts::new(*name, *help), LABELS).unwrap())))
+                .collect(),
+            replica_lag: gauge("redis_replica_lag_bytes", "Bytes a replica is behind its master's offset"),
+            exported: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn observe(&self, cluster: &str, reports: &[NodeReport]) {
+        let mut all: HashSet<LabelSet> = HashSet::new();
+        let mut reachable: HashSet<LabelSet> = HashSet::new();
+        for report in reports {
+            let labels: LabelSet = [cluster.to_string(), report.ip.clone(), report.port.clone(), report.role.clone()];
+            let up: bool = report.status == NodeStatus::Up;
+            self.up.with_label_values(&labels).set(up as i64);
+            all.insert(labels.clone());
+            if !up {
+                continue;
+            }
+            for (field, gauge) in &self.gauges {
+                if let Some(v) = report.info.get(*field).and_then(|v| v.parse::<f64>().ok()) {
+                    gauge.with_label_values(&labels).set(v);
+                }
+            }
+            for (field, counter) in &self.counters {
+                if let Some(v) = report.info.get(*field).and_then(|v| v.parse::<f64>().ok()) {
+                    let counter = counter.with_label_values(&labels);
+                    // INFO reports absolute totals; a drop means the node restarted.
+                    let current: f64 = counter.get();
+                    if v < current {
+                        counter.reset();
+                        counter.inc_by(v);
+                    } else {
+                        counter.inc_by(v - current);
+                    }
+                }
+            }
+            match report.replication.as_ref().and_then(|r| r.lag_bytes) {
+                Some(lag) => self.replica_lag.with_label_values(&labels).set(lag as f64),
+                None => {
+                    let _ = self.replica_lag.remove_label_values(&labels);
+                }
+            }
+            reachable.insert(labels);
+        }
+
+        let mut exported = self.exported.lock().unwrap();
+        let prev: Exported = exported.remove(cluster).unwrap_or_default();
+        for labels in prev.reachable.difference(&reachable) {
+            for (_, gauge) in &self.gauges {
+                let _ = gauge.remove_label_values(labels);
+            }
+            for (_, counter) in &self.counters {
+                let _ = counter.remove_label_values(labels);
+            }
+            let _ = self.replica_lag.remove_label_values(labels);
+        }
+        for labels in prev.all.difference(&all) {
+            let _ = self.up.remove_label_values(labels);
+        }
+        exported.insert(cluster.to_string(), Exported { all, reachable });
+    }
+}
+
+/// Publishes the parsed INFO fields of a snapshot.
+///
+/// Series of nodes that left the topology or changed role are removed, and
+/// unreachable nodes keep only `redis_up`, so no stale values are scraped.
+pub(crate) fn observe(cluster: &str, reports: &[NodeReport]) {
+    METRICS.observe(cluster, reports);
+}
+
+/// Renders every registered metric in the Prometheus text format.
+pub(crate) fn render() -> (String, Vec<u8>) {
+    let encoder = TextEncoder::new();
+    let mut buffer: Vec<u8> = Vec::new();
+    encoder.encode(&prometheus::gather(), &mut buffer)
+        .expect("Failed to encode metrics");
+    (encoder.format_type().to_string(), buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::NodeProbe, error::CollectError, RedisInstance};
+
+    fn instance(port: &str) -> RedisInstance {
+        RedisInstance { ip: "10.0.0.1".to_string(), port: port.to_string(), ..Default::default() }
+    }
+
+    fn up(port: &str, role: &str, commands: u64) -> NodeReport {
+        let info: HashMap<String, String> = [
+            ("role".to_string(), role.to_string()),
+            ("used_memory".to_string(), "1000".to_string()),
+            ("total_commands_processed".to_string(), commands.to_string()),
+        ].into();
+        NodeReport::new(&instance(port), role, None, Ok(NodeProbe { info, ..Default::default() }))
+    }
+
+    fn down(port: &str) -> NodeReport {
+        NodeReport::new(&instance(port), "slave", None, Err(CollectError::Timeout("no reply within 1000 ms".to_string())))
+    }
+
+    fn scrape(registry: &Registry) -> String {
+        let mut buffer: Vec<u8> = Vec::new();
+        TextEncoder::new().encode(&registry.gather(), &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    // Value of the one series of `name` whose labels contain `labels`, if exported
+    fn value(text: &str, name: &str, labels: &str) -> Option<f64> {
+        text.lines()
+            .filter(|line| line.starts_with(&format!("{}{{", name)) && line.contains(labels))
+            .map(|line| line.rsplit_once(' ').unwrap().1.parse().unwrap())
+            .next()
+    }
+
+    #[test]
+    fn info_fields_are_exported_per_node() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry);
+        metrics.observe("s1", &[up("6379", "master", 100), down("6380")]);
+        let text: String = scrape(&registry);
+        assert_eq!(value(&text, "redis_up", r#"port="6379",role="master""#), Some(1.0));
+        assert_eq!(value(&text, "redis_memory_used_bytes", r#"port="6379""#), Some(1000.0));
+        assert_eq!(value(&text, "redis_commands_processed_total", r#"port="6379""#), Some(100.0));
+        // Unreachable nodes only keep `redis_up`
+        assert_eq!(value(&text, "redis_up", r#"port="6380",role="slave""#), Some(0.0));
+        assert_eq!(value(&text, "redis_memory_used_bytes", r#"port="6380""#), None);
+    }
+
+    #[test]
+    fn nodes_that_leave_lose_their_series() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry);
+        metrics.observe("s1", &[up("6379", "master", 100), up("6380", "slave", 5)]);
+        metrics.observe("s1", &[up("6379", "master", 200)]);
+        let text: String = scrape(&registry);
+        assert_eq!(value(&text, "redis_up", r#"port="6380""#), None);
+        assert_eq!(value(&text, "redis_memory_used_bytes", r#"port="6380""#), None);
+        assert_eq!(value(&text, "redis_commands_processed_total", r#"port="6379""#), Some(200.0));
+
+        // Another cluster's series are not touched
+        metrics.observe("s2", &[up("6380", "master", 1)]);
+        metrics.observe("s2", &[]);
+        assert_eq!(value(&scrape(&registry), "redis_up", r#"cluster="s1",ip="10.0.0.1",port="6379""#), Some(1.0));
+    }
+
+    #[test]
+    fn a_role_change_relabels_the_node() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry);
+        metrics.observe("s1", &[up("6379", "master", 100)]);
+        metrics.observe("s1", &[up("6379", "slave", 150)]);
+        let text: String = scrape(&registry);
+        assert_eq!(value(&text, "redis_up", r#"role="master""#), None);
+        assert_eq!(value(&text, "redis_memory_used_bytes", r#"role="master""#), None);
+        assert_eq!(value(&text, "redis_up", r#"role="slave""#), Some(1.0));
+        assert_eq!(value(&text, "redis_commands_processed_total", r#"role="slave""#), Some(150.0));
+    }
+
+    #[test]
+    fn counters_follow_a_restart() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry);
+        metrics.observe("s1", &[up("6379", "master", 5000)]);
+        metrics.observe("s1", &[up("6379", "master", 20)]);
+        assert_eq!(value(&scrape(&registry), "redis_commands_processed_total", r#"port="6379""#), Some(20.0));
+        metrics.observe("s1", &[up("6379", "master", 70)]);
+        assert_eq!(value(&scrape(&registry), "redis_commands_processed_total", r#"port="6379""#), Some(70.0));
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use crate::collector::{NodeReport, NodeStatus};
+
+// Entries kept per cluster; older ones are dropped first.
+const LOG_LEN: usize = 1000;
+
+/// An entry of a `NodeLog`.
+pub(crate) trait Logged: Clone {
+    /// Unix milliseconds the entry happened at.
+    fn ts(&self) -> u64;
+}
+
+struct Inner<S, T> {
+    nodes: HashMap<String, S>,
+    entries: VecDeque<T>,
+}
+
+/// Bounded log of what the nodes of one cluster reported, oldest first,
+/// with the state each node is compared against on the next tick.
+pub(crate) struct NodeLog<S, T> {
+    inner: Mutex<Inner<S, T>>,
+}
+
+impl<S, T> Default for NodeLog<S, T> {
+    fn default() -> Self {
+        NodeLog { inner: Mutex::new(Inner { nodes: HashMap::new(), entries: VecDeque::new() }) }
+    }
+}
+
+impl<S: Default, T: Logged> NodeLog<S, T> {
+    /// Scans every reachable node against its state and logs what `scan` found.
+    ///
+    /// Unreachable nodes keep their state, nodes that left the topology are forgotten.
+    pub(crate) fn observe(&self, reports: &[NodeReport], mut scan: impl FnMut(&NodeReport, &mut S) -> Vec<T>) -> Vec<T> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut found: Vec<T> = Vec::new();
+        for report in reports.iter().filter(|r| r.status == NodeStatus::Up) {
+            found.extend(scan(report, inner.nodes.entry(report.addr()).or_default()));
+        }
+        inner.nodes.retain(|node, _| reports.iter().any(|r| r.addr() == *node));
+        found.sort_by_key(|entry| entry.ts());
+        inner.entries.extend(found.iter().cloned());
+        let overflow: usize = inner.entries.len().saturating_sub(LOG_LEN);
+        inner.entries.drain(..overflow);
+        found
+    }
+
+    /// `read` applied to the state of `node`, `None` until the node has been observed.
+    pub(crate) fn state<R>(&self, node: &str, read: impl FnOnce(&S) -> R) -> Option<R> {
+        self.inner.lock().unwrap().nodes.get(node).map(read)
+    }
+
+    /// Logged entries newer than `since` (unix milliseconds), oldest first.
+    pub(crate) fn since(&self, since: u64) -> Vec<T> {
+        self.inner.lock().unwrap().entries.iter()
+            .filter(|entry| entry.ts() > since)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::CollectError, RedisInstance};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Tick(u64);
+
+    impl Logged for Tick {
+        fn ts(&self) -> u64 {
+            self.0
+        }
+    }
+
+    fn report(port: &str, up: bool) -> NodeReport {
+        let instance = RedisInstance { ip: "10.0.0.1".to_string(), port: port.to_string(), ..Default::default() };
+        let probe = if up {
+            Ok(Default::default())
+        } else {
+            Err(CollectError::Timeout("no reply within 1000 ms".to_string()))
+        };
+        NodeReport::new(&instance, "master", None, probe)
+    }
+
+    // Logs one entry per reachable node and counts its polls in the state.
+    fn tick(log: &NodeLog<u64, Tick>, reports: &[NodeReport], ts: u64) -> Vec<Tick> {
+        log.observe(reports, |_, polls| {
+            *polls += 1;
+            vec![Tick(ts)]
+        })
+    }
+
+    #[test]
+    fn the_oldest_entries_are_dropped_first() {
+        let log: NodeLog<u64, Tick> = NodeLog::default();
+        for ts in 1..=LOG_LEN as u64 + 5 {
+            tick(&log, &[report("6379", true)], ts);
+        }
+        let entries: Vec<Tick> = log.since(0);
+        assert_eq!(entries.len(), LOG_LEN);
+        assert_eq!(entries[0], Tick(6));
+        assert_eq!(log.since(LOG_LEN as u64 + 3), [Tick(LOG_LEN as u64 + 4), Tick(LOG_LEN as u64 + 5)]);
+    }
+
+    #[test]
+    fn unreachable_nodes_keep_their_state_and_departed_ones_are_forgotten() {
+        let log: NodeLog<u64, Tick> = NodeLog::default();
+        tick(&log, &[report("6379", true), report("6380", true)], 1);
+        assert_eq!(tick(&log, &[report("6379", true), report("6380", false)], 2), [Tick(2)]);
+        assert_eq!(log.state("10.0.0.1:6379", |polls| *polls), Some(2));
+        assert_eq!(log.state("10.0.0.1:6380", |polls| *polls), Some(1));
+
+        tick(&log, &[report("6379", true)], 3);
+        assert_eq!(log.state("10.0.0.1:6380", |polls| *polls), None);
+    }
+}
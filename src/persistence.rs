@@ -0,0 +1,109 @@
+use crate::{
+    collector::{NodeReport, NodeStatus},
+    history::info_num,
+};
+
+fn failed(report: &NodeReport, field: &str) -> bool {
+    report.info.get(field).is_some_and(|v| v == "err")
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        s if s >= 86400 => format!("{}d {}h", s / 86400, s % 86400 / 3600),
+        s if s >= 3600 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m {}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// What is wrong with the persistence of a reachable node, from its INFO.
+///
+/// With `max_save_age_secs` set, unsaved changes older than that are
+/// reported too; nodes that are not meant to persist would otherwise warn
+/// forever, so it is off by default.
+pub(crate) fn warnings(report: &NodeReport, max_save_age_secs: u64, ts: u64) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    if report.status != NodeStatus::Up {
+        return found;
+    }
+    if failed(report, "rdb_last_bgsave_status") {
+        found.push("the last BGSAVE failed".to_string());
+    }
+    if info_num::<u8>(&report.info, "aof_enabled") == 1 {
+        if failed(report, "aof_last_bgrewrite_status") {
+            found.push("the last AOF rewrite failed".to_string());
+        }
+        if failed(report, "aof_last_write_status") {
+            found.push("the last AOF write failed".to_string());
+        }
+    }
+    let changes: u64 = info_num(&report.info, "rdb_changes_since_last_save");
+    let last_save: u64 = info_num(&report.info, "rdb_last_save_time");
+    let age: u64 = (ts / 1000).saturating_sub(last_save);
+    if max_save_age_secs > 0 && changes > 0 && last_save > 0 && age > max_save_age_secs {
+        found.push(format!("no RDB save for {} with {} changes pending", format_age(age), changes));
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collector::{parse_redis_info, NodeProbe},
+        error::CollectError,
+        RedisInstance,
+    };
+
+    const HEALTHY: &str = "# Persistence\r\nloading:0\r\nrdb_changes_since_last_save:40\r\n\
+        rdb_bgsave_in_progress:0\r\nrdb_last_save_time:1700000000\r\nrdb_last_bgsave_status:ok\r\n\
+        aof_enabled:1\r\naof_rewrite_in_progress:0\r\naof_last_bgrewrite_status:ok\r\naof_last_write_status:ok\r\n";
+    // Unix milliseconds 90 seconds after the last save.
+    const NOW: u64 = 1_700_000_090_000;
+
+    fn instance() -> RedisInstance {
+        RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() }
+    }
+
+    fn up(info: &str) -> NodeReport {
+        NodeReport::new(&instance(), "master", None, Ok(NodeProbe { info: parse_redis_info(info), ..Default::default() }))
+    }
+
+    #[test]
+    fn a_healthy_node_has_no_warnings() {
+        assert!(warnings(&up(HEALTHY), 0, NOW).is_empty());
+        assert!(warnings(&up(HEALTHY), 120, NOW).is_empty());
+    }
+
+    #[test]
+    fn failed_saves_and_aof_errors_are_always_warned_about() {
+        let failing: String = HEALTHY
+            .replace("rdb_last_bgsave_status:ok", "rdb_last_bgsave_status:err")
+            .replace("aof_last_bgrewrite_status:ok", "aof_last_bgrewrite_status:err")
+            .replace("aof_last_write_status:ok", "aof_last_write_status:err");
+        assert_eq!(
+            warnings(&up(&failing), 0, NOW),
+            ["the last BGSAVE failed", "the last AOF rewrite failed", "the last AOF write failed"],
+        );
+        // AOF statuses are stale once AOF is turned off
+        let aof_off: String = failing.replace("aof_enabled:1", "aof_enabled:0");
+        assert_eq!(warnings(&up(&aof_off), 0, NOW), ["the last BGSAVE failed"]);
+    }
+
+    #[test]
+    fn stale_saves_are_only_warned_about_with_a_limit_and_pending_changes() {
+        assert_eq!(warnings(&up(HEALTHY), 60, NOW), ["no RDB save for 1m 30s with 40 changes pending"]);
+        assert_eq!(warnings(&up(HEALTHY), 60, NOW + 2 * 86400 * 1000)[0], "no RDB save for 2d 0h with 40 changes pending");
+        assert!(warnings(&up(HEALTHY), 90, NOW).is_empty());
+        let saved: String = HEALTHY.replace("rdb_changes_since_last_save:40", "rdb_changes_since_last_save:0");
+        assert!(warnings(&up(&saved), 60, NOW).is_empty());
+    }
+
+    #[test]
+    fn unreachable_nodes_have_no_warnings() {
+        let down = NodeReport::new(&instance(), "master", None, Err(CollectError::Timeout("no reply within 1000 ms".to_string())));
+        assert!(warnings(&down, 60, NOW).is_empty());
+        assert_eq!(format_age(3 * 3600 + 120), "3h 2m");
+        assert_eq!(format_age(42), "42s");
+    }
+}
@@ -0,0 +1,218 @@
+use redis::{aio::MultiplexedConnection, Client, RedisError};
+use std::{
+    collections::HashMap,
+    net::ToSocketAddrs,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{addr, error::CollectError, RedisCluster, RedisConfig, RedisInstance};
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+// Connections to nodes that left the topology are closed after this long.
+const IDLE_EVICTION: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+struct Slot {
+    con: Option<MultiplexedConnection>,
+    failures: u32,
+    retry_at: Option<Instant>,
+    last_error: Option<CollectError>,
+    last_used: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timeouts {
+    pub(crate) connect: Duration,
+    pub(crate) command: Duration,
+}
+
+/// Long-lived connections for one cluster, keyed by `ip:port`.
+///
+/// A multiplexed connection is opened (and authenticated) once per node and
+/// shared by every tick. Failed connects back off exponentially so a dead
+/// node is not hammered with reconnects.
+pub(crate) struct ConnectionPool {
+    password: String,
+    timeouts: Timeouts,
+    // Nodes listed in the config may override the cluster timeouts, keyed by
+    // the configured `host:port` and every `ip:port` the host resolves to
+    node_timeouts: HashMap<String, Timeouts>,
+    slots: Mutex<HashMap<String, Slot>>,
+}
+
+impl ConnectionPool {
+    pub(crate) fn new(cluster: &RedisCluster, config: &RedisConfig) -> Self {
+        Self::with_password(cluster, config, cluster.password.clone())
+    }
+
+    /// Pool for the Sentinels of a cluster, which may use their own password.
+    pub(crate) fn for_sentinels(cluster: &RedisCluster, config: &RedisConfig) -> Self {
+        let password: String = cluster.sentinel_password.clone()
+            .unwrap_or_else(|| cluster.password.clone());
+        Self::with_password(cluster, config, password)
+    }
+
+    fn with_password(cluster: &RedisCluster, config: &RedisConfig, password: String) -> Self {
+        let timeouts = Timeouts {
+            connect: Duration::from_millis(cluster.connect_timeout_ms.unwrap_or(config.connect_timeout_ms)),
+            command: Duration::from_millis(cluster.command_timeout_ms.unwrap_or(config.command_timeout_ms)),
+        };
+        let mut node_timeouts: HashMap<String, Timeouts> = HashMap::new();
+        for instance in &cluster.instances {
+            let node = Timeouts {
+                connect: instance.connect_timeout_ms.map(Duration::from_millis).unwrap_or(timeouts.connect),
+                command: instance.command_timeout_ms.map(Duration::from_millis).unwrap_or(timeouts.command),
+            };
+            let host: &str = addr::normalize_host(&instance.ip);
+            node_timeouts.insert(format!("{}:{}", host, instance.port), node);
+            // Discovered nodes are announced by IP, so a seed configured by
+            // hostname is also matched under the addresses it resolves to.
+            let resolved = instance.port.parse::<u16>().ok()
+                .and_then(|port| (host, port).to_socket_addrs().ok())
+                .into_iter()
+                .flatten();
+            for ip in resolved {
+                node_timeouts.entry(format!("{}:{}", ip.ip(), instance.port)).or_insert(node);
+            }
+        }
+        ConnectionPool {
+            password,
+            timeouts,
+            node_timeouts,
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Timeouts for `instance`, whether it came from the config or was discovered.
+    pub(crate) fn timeouts(&self, instance: &RedisInstance) -> Timeouts {
+        self.node_timeouts.get(&format!("{}:{}", addr::normalize_host(&instance.ip), instance.port))
+            .copied()
+            .unwrap_or(self.timeouts)
+    }
+
+    fn uri(&self, instance: &RedisInstance) -> String {
+        if self.password.is_empty() {
+            format!("redis://{}:{}", addr::uri_host(&instance.ip), instance.port)
+        } else {
+            format!("redis://default:{}@{}:{}", self.password, addr::uri_host(&instance.ip), instance.port)
+        }
+    }
+
+    /// Returns the cached connection for `instance`, connecting if needed.
+    ///
+    /// While a node is inside its backoff window the last connect error is
+    /// returned without touching the network.
+    pub(crate) async fn get(&self, instance: &RedisInstance) -> Result<MultiplexedConnection, CollectError> {
+        let key: String = format!("{}:{}", instance.ip, instance.port);
+        let now: Instant = Instant::now();
+        {
+            let mut slots = self.slots.lock().unwrap();
+            let slot: &mut Slot = slots.entry(key.clone()).or_default();
+            slot.last_used = Some(now);
+            if let Some(con) = &slot.con {
+                return Ok(con.clone());
+            }
+            if slot.retry_at.is_some_and(|at| now < at)
+                && let Some(e) = &slot.last_error {
+                return Err(e.clone());
+            }
+        }
+
+        let limit: Duration = self.timeouts(instance).connect;
+        let connected: Result<MultiplexedConnection, CollectError> = match Client::open(self.uri(instance)) {
+            Ok(client) => match tokio::time::timeout(limit, client.get_multiplexed_tokio_connection()).await {
+                Ok(con) => con.map_err(CollectError::from),
+                Err(_) => Err(CollectError::Timeout(format!("connect not completed within {} ms", limit.as_millis()))),
+            },
+            Err(e) => Err(CollectError::from(e)),
+        };
+
+        let mut slots = self.slots.lock().unwrap();
+        let slot: &mut Slot = slots.entry(key).or_default();
+        match &connected {
+            Ok(con) => {
+                slot.con = Some(con.clone());
+                slot.failures = 0;
+                slot.retry_at = None;
+                slot.last_error = None;
+            }
+            Err(e) => {
+                slot.failures = slot.failures.saturating_add(1);
+                let backoff: Duration = BACKOFF_BASE
+                    .saturating_mul(1 << (slot.failures - 1).min(16))
+                    .min(BACKOFF_MAX);
+                slot.retry_at = Some(Instant::now() + backoff);
+                slot.last_error = Some(e.clone());
+            }
+        }
+        connected
+    }
+
+    /// Drops the cached connection after a command error that leaves it unusable.
+    pub(crate) fn invalidate(&self, instance: &RedisInstance, err: &RedisError) {
+        if err.is_io_error() || err.is_connection_dropped() || err.is_connection_refusal() {
+            self.discard(instance);
+        }
+    }
+
+    /// Drops the cached connection unconditionally, e.g. after a timeout.
+    pub(crate) fn discard(&self, instance: &RedisInstance) {
+        let key: String = format!("{}:{}", instance.ip, instance.port);
+        if let Some(slot) = self.slots.lock().unwrap().get_mut(&key) {
+            slot.con = None;
+        }
+    }
+
+    /// Closes connections to nodes that have not been polled for a while.
+    pub(crate) fn evict_idle(&self) {
+        let now: Instant = Instant::now();
+        self.slots.lock().unwrap()
+            .retain(|_, slot| slot.last_used.is_some_and(|t| now.duration_since(t) < IDLE_EVICTION));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pool(instances: serde_json::Value) -> ConnectionPool {
+        let config: RedisConfig = serde_json::from_value(json!({
+            "clusters": [{ "name": "s1", "instances": instances, "password": "", "command_timeout_ms": 500 }],
+        })).unwrap();
+        ConnectionPool::new(&config.clusters[0], &config)
+    }
+
+    fn node(ip: &str, port: &str) -> RedisInstance {
+        RedisInstance { ip: ip.to_string(), port: port.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn the_most_specific_timeout_wins() {
+        let pool: ConnectionPool = pool(json!([
+            { "ip": "10.0.0.1", "port": "6379", "command_timeout_ms": 3000 },
+            { "ip": "10.0.0.2", "port": "6379" },
+        ]));
+        assert_eq!(pool.timeouts(&node("10.0.0.1", "6379")).command, Duration::from_millis(3000));
+        assert_eq!(pool.timeouts(&node("10.0.0.1", "6379")).connect, Duration::from_millis(1000));
+        assert_eq!(pool.timeouts(&node("10.0.0.2", "6379")).command, Duration::from_millis(500));
+        // Discovered, not configured
+        assert_eq!(pool.timeouts(&node("10.0.0.3", "6379")).command, Duration::from_millis(500));
+        assert_eq!(pool.timeouts(&node("10.0.0.1", "6380")).command, Duration::from_millis(500));
+    }
+
+    #[test]
+    fn discovered_addresses_match_the_configured_host() {
+        let pool: ConnectionPool = pool(json!([
+            { "ip": "[::1]", "port": "7000", "command_timeout_ms": 3000 },
+            { "ip": "localhost", "port": "7001", "command_timeout_ms": 4000 },
+        ]));
+        // CLUSTER NODES writes IPv6 without brackets
+        assert_eq!(pool.timeouts(&node("::1", "7000")).command, Duration::from_millis(3000));
+        assert_eq!(pool.timeouts(&node("[::1]", "7000")).command, Duration::from_millis(3000));
+        assert_eq!(pool.timeouts(&node("localhost", "7001")).command, Duration::from_millis(4000));
+        assert_eq!(pool.timeouts(&node("127.0.0.1", "7001")).command, Duration::from_millis(4000));
+    }
+}
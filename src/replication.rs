@@ -0,0 +1,159 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    collector::{InfoMap, NodeReport, NodeStatus},
+    history::info_num,
+};
+
+/// Replication state of a replica, from its own INFO and its master's.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ReplicaStatus {
+    /// `master_link_status`, `up` or `down`.
+    pub(crate) link_status: String,
+    /// Seconds since the last interaction with the master, absent while the link is down.
+    pub(crate) last_io_secs: Option<u64>,
+    /// Seconds the link has been down for.
+    pub(crate) link_down_secs: Option<u64>,
+    pub(crate) sync_in_progress: bool,
+    /// `slave_repl_offset`
+    pub(crate) offset: u64,
+    /// Bytes behind the master's `master_repl_offset`, absent while the master is unreachable.
+    pub(crate) lag_bytes: Option<u64>,
+    /// Whether the master's backlog still starts at or before the replica's
+    /// offset, so a reconnect can resume with a partial sync.
+    pub(crate) in_backlog: Option<bool>,
+}
+
+// What a replica's lag is measured against.
+struct MasterOffsets {
+    offset: u64,
+    // First offset still in the backlog, absent without an active backlog
+    backlog_start: Option<u64>,
+}
+
+// `master_last_io_seconds_ago` and `master_link_down_since_seconds` are -1 when not applicable.
+fn secs(info: &InfoMap, key: &str) -> Option<u64> {
+    info.get(key).and_then(|v| v.trim().parse::<i64>().ok()).and_then(|v| u64::try_from(v).ok())
+}
+
+/// Fills in the replication state of every reachable replica.
+///
+/// The lag is taken against the master the replica was discovered from,
+/// polled on the same tick.
+pub(crate) fn link(reports: &mut [NodeReport]) {
+    let masters: HashMap<String, MasterOffsets> = reports.iter()
+        .filter(|r| r.status == NodeStatus::Up && r.role == "master")
+        .map(|r| {
+            let offsets = MasterOffsets {
+                offset: info_num(&r.info, "master_repl_offset"),
+                backlog_start: Some(info_num(&r.info, "repl_backlog_first_byte_offset"))
+                    .filter(|_| info_num::<u8>(&r.info, "repl_backlog_active") == 1),
+            };
+            (r.addr(), offsets)
+        })
+        .collect();
+    for report in reports.iter_mut() {
+        if report.status != NodeStatus::Up || report.role != "slave" {
+            continue;
+        }
+        let info: &InfoMap = &report.info;
+        let offset: u64 = info_num(info, "slave_repl_offset");
+        let master: Option<&MasterOffsets> = report.master.as_ref().and_then(|m| masters.get(m));
+        report.replication = Some(ReplicaStatus {
+            link_status: info.get("master_link_status").cloned().unwrap_or_default(),
+            last_io_secs: secs(info, "master_last_io_seconds_ago"),
+            link_down_secs: secs(info, "master_link_down_since_seconds"),
+            sync_in_progress: info_num::<u8>(info, "master_sync_in_progress") == 1,
+            offset,
+            lag_bytes: master.map(|m| m.offset.saturating_sub(offset)),
+            // A partial sync resumes at the byte after the replica's offset.
+            in_backlog: master.map(|m| m.backlog_start.is_some_and(|start| offset + 1 >= start)),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collector::{parse_redis_info, NodeProbe},
+        error::CollectError,
+        RedisInstance,
+    };
+
+    const MASTER: &str = "role:master\r\nconnected_slaves:2\r\nmaster_repl_offset:50000\r\n\
+        repl_backlog_active:1\r\nrepl_backlog_size:1048576\r\nrepl_backlog_first_byte_offset:20001\r\n";
+
+    fn instance(port: &str) -> RedisInstance {
+        RedisInstance { ip: "10.0.0.1".to_string(), port: port.to_string(), ..Default::default() }
+    }
+
+    fn up(port: &str, master: Option<&str>, info: &str) -> NodeReport {
+        let probe = NodeProbe { info: parse_redis_info(info), ..Default::default() };
+        NodeReport::new(&instance(port), "slave", master.map(String::from), Ok(probe))
+    }
+
+    fn replica(offset: u64, link: &str) -> String {
+        format!(
+            "role:slave\r\nmaster_host:10.0.0.1\r\nmaster_port:6379\r\nmaster_link_status:{}\r\n\
+            master_last_io_seconds_ago:{}\r\nmaster_sync_in_progress:0\r\nslave_repl_offset:{}\r\n\
+            master_link_down_since_seconds:{}\r\n",
+            link,
+            if link == "up" { "1" } else { "-1" },
+            offset,
+            if link == "up" { "-1" } else { "42" },
+        )
+    }
+
+    fn status(reports: &[NodeReport], port: &str) -> ReplicaStatus {
+        reports.iter().find(|r| r.port == port).unwrap().replication.clone().unwrap()
+    }
+
+    #[test]
+    fn lag_is_measured_against_the_masters_offset() {
+        let mut reports: Vec<NodeReport> = vec![
+            up("6379", None, MASTER),
+            up("6380", Some("10.0.0.1:6379"), &replica(49_000, "up")),
+            up("6381", Some("10.0.0.1:6379"), &replica(10_000, "down")),
+        ];
+        link(&mut reports);
+        assert!(reports[0].replication.is_none());
+
+        let near: ReplicaStatus = status(&reports, "6380");
+        assert_eq!((near.link_status.as_str(), near.last_io_secs, near.link_down_secs), ("up", Some(1), None));
+        assert_eq!((near.offset, near.lag_bytes, near.in_backlog), (49_000, Some(1000), Some(true)));
+
+        let far: ReplicaStatus = status(&reports, "6381");
+        assert_eq!((far.link_status.as_str(), far.last_io_secs, far.link_down_secs), ("down", None, Some(42)));
+        assert_eq!((far.lag_bytes, far.in_backlog), (Some(40_000), Some(false)));
+    }
+
+    #[test]
+    fn the_backlog_starts_right_after_the_last_byte_a_replica_has() {
+        let mut reports: Vec<NodeReport> = vec![
+            up("6379", None, MASTER),
+            up("6380", Some("10.0.0.1:6379"), &replica(20_000, "up")),
+            up("6381", Some("10.0.0.1:6379"), &replica(19_999, "up")),
+        ];
+        link(&mut reports);
+        assert_eq!(status(&reports, "6380").in_backlog, Some(true));
+        assert_eq!(status(&reports, "6381").in_backlog, Some(false));
+
+        let mut inactive: Vec<NodeReport> = vec![
+            up("6379", None, &MASTER.replace("repl_backlog_active:1", "repl_backlog_active:0")),
+            up("6380", Some("10.0.0.1:6379"), &replica(49_000, "up")),
+        ];
+        link(&mut inactive);
+        assert_eq!(status(&inactive, "6380").in_backlog, Some(false));
+    }
+
+    #[test]
+    fn no_lag_without_a_reachable_master() {
+        let down = NodeReport::new(&instance("6379"), "master", None, Err(CollectError::Timeout("no reply within 1000 ms".to_string())));
+        let mut reports: Vec<NodeReport> = vec![down, up("6380", Some("10.0.0.1:6379"), &replica(49_000, "down"))];
+        link(&mut reports);
+        let orphan: ReplicaStatus = status(&reports, "6380");
+        assert_eq!((orphan.offset, orphan.lag_bytes, orphan.in_backlog), (49_000, None, None));
+    }
+}
@@ -0,0 +1,20 @@
+use redis::Value;
+
+/// A non-negative integer reply, also when sent as a bulk string.
+pub(crate) fn int(value: &Value) -> Option<u64> {
+    match value {
+        Value::Int(n) => u64::try_from(*n).ok(),
+        Value::Data(bytes) => std::str::from_utf8(bytes).ok()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// A string reply; integers are written out.
+pub(crate) fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Data(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        Value::Status(s) => Some(s.clone()),
+        Value::Int(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,397 @@
+use std::fmt;
+
+use crate::collector::{InfoMap, NodeReport};
+
+/// Arithmetic over INFO fields, e.g. `used_memory / maxmemory`.
+#[derive(Debug, Clone)]
+enum Expr {
+    Num(f64),
+    /// An INFO field, or `status` / `role` of the node.
+    Field(String),
+    Text(String),
+    /// Per-second increase of a counter since the previous poll.
+    Rate(String),
+    Bin(Box<Expr>, char, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+/// A parsed alert condition: `<expr> <cmp> <expr> [for <duration>]`.
+///
+/// A bare word on the right-hand side that is not an INFO field is compared
+/// as text, so `master_link_status != up` reads naturally.
+#[derive(Debug, Clone)]
+pub(crate) struct Condition {
+    lhs: Expr,
+    cmp: Cmp,
+    rhs: Expr,
+    /// How long the condition must hold before the alert fires.
+    pub(crate) hold_ms: u64,
+}
+
+/// What a condition is evaluated against.
+pub(crate) struct Target<'a> {
+    pub(crate) report: &'a NodeReport,
+    /// INFO of the previous poll and when it was taken, for `rate()`.
+    pub(crate) prev: Option<(&'a InfoMap, u64)>,
+    pub(crate) ts: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Num(f64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Word(String),
+    Text(String),
+    Op(&'static str),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i: usize = 0;
+    while i < chars.len() {
+        let c: char = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start: usize = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Num(text.parse().map_err(|_| format!("invalid number '{}'", text))?));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start: usize = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if c == '"' || c == '\'' {
+            let end: usize = chars[i + 1..].iter().position(|q| *q == c)
+                .ok_or_else(|| "unterminated string".to_string())? + i + 1;
+            tokens.push(Token::Text(chars[i + 1..end].iter().collect()));
+            i = end + 1;
+        } else {
+            let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            let op: &'static str = match (two.as_str(), c) {
+                (">=", _) => ">=",
+                ("<=", _) => "<=",
+                ("==", _) => "==",
+                ("!=", _) => "!=",
+                (_, '>') => ">",
+                (_, '<') => "<",
+                (_, '+') => "+",
+                (_, '-') => "-",
+                (_, '*') => "*",
+                (_, '/') => "/",
+                (_, '(') => "(",
+                (_, ')') => ")",
+                _ => return Err(format!("unexpected character '{}'", c)),
+            };
+            i += op.len();
+            tokens.push(Token::Op(op));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs: Expr = self.product()?;
+        while let Some(Token::Op(op @ ("+" | "-"))) = self.peek() {
+            let op: char = op.chars().next().unwrap_or('+');
+            self.pos += 1;
+            lhs = Expr::Bin(Box::new(lhs), op, Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    // product := atom (('*' | '/') atom)*
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs: Expr = self.atom()?;
+        while let Some(Token::Op(op @ ("*" | "/"))) = self.peek() {
+            let op: char = op.chars().next().unwrap_or('*');
+            self.pos += 1;
+            lhs = Expr::Bin(Box::new(lhs), op, Box::new(self.atom()?));
+        }
+        Ok(lhs)
+    }
+
+    // atom := number | 'string' | field | rate(field) | '(' sum ')'
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::Text(s)) => Ok(Expr::Text(s)),
+            Some(Token::Word(w)) if w == "rate" && self.eat("(") => {
+                let Some(Token::Word(field)) = self.next() else {
+                    return Err("rate() takes an INFO field".to_string());
+                };
+                if !self.eat(")") {
+                    return Err("missing ')' after rate(".to_string());
+                }
+                Ok(Expr::Rate(field))
+            }
+            Some(Token::Word(w)) => Ok(Expr::Field(w)),
+            Some(Token::Op("(")) => {
+                let inner: Expr = self.sum()?;
+                if !self.eat(")") {
+                    return Err("missing ')'".to_string());
+                }
+                Ok(inner)
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn parse_duration_ms(text: &str) -> Result<u64, String> {
+    let split: usize = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (num, unit) = text.split_at(split);
+    let n: u64 = num.parse().map_err(|_| format!("invalid duration '{}'", text))?;
+    let scale: u64 = match unit {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 3600 * 1000,
+        _ => return Err(format!("invalid duration unit '{}'", unit)),
+    };
+    n.checked_mul(scale).ok_or_else(|| format!("duration '{}' is too long", text))
+}
+
+/// Parses a rule condition such as `used_memory / maxmemory > 0.9 for 60s`.
+pub(crate) fn parse(src: &str) -> Result<Condition, String> {
+    let (body, hold_ms) = match src.rsplit_once(" for ") {
+        Some((body, hold)) => (body, parse_duration_ms(hold.trim())?),
+        None => (src, 0),
+    };
+    let mut parser = Parser { tokens: tokenize(body)?, pos: 0 };
+    let lhs: Expr = parser.sum()?;
+    let cmp: Cmp = match parser.next() {
+        Some(Token::Op(">")) => Cmp::Gt,
+        Some(Token::Op(">=")) => Cmp::Ge,
+        Some(Token::Op("<")) => Cmp::Lt,
+        Some(Token::Op("<=")) => Cmp::Le,
+        Some(Token::Op("==")) => Cmp::Eq,
+        Some(Token::Op("!=")) => Cmp::Ne,
+        _ => return Err("expected one of > >= < <= == !=".to_string()),
+    };
+    let rhs: Expr = parser.sum()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected {:?} after the comparison", token));
+    }
+    Ok(Condition { lhs, cmp, rhs, hold_ms })
+}
+
+fn number(info: &InfoMap, field: &str) -> Option<f64> {
+    info.get(field)?.trim().parse().ok()
+}
+
+fn eval(expr: &Expr, target: &Target) -> Option<Value> {
+    let report: &NodeReport = target.report;
+    match expr {
+        Expr::Num(n) => Some(Value::Num(*n)),
+        Expr::Text(s) => Some(Value::Text(s.clone())),
+        Expr::Field(f) if f == "status" => Some(Value::Text(report.status.as_str().to_string())),
+        Expr::Field(f) if f == "role" => Some(Value::Text(report.role.clone())),
+        Expr::Field(f) => {
+            let raw: &String = report.info.get(f)?;
+            Some(raw.trim().parse().map(Value::Num).unwrap_or_else(|_| Value::Text(raw.clone())))
+        }
+        Expr::Rate(f) => {
+            let (prev, prev_ts) = target.prev?;
+            let secs: f64 = target.ts.checked_sub(prev_ts).filter(|d| *d > 0)? as f64 / 1000.0;
+            // A counter going backwards means a restart; count it as no activity.
+            Some(Value::Num((number(&report.info, f)? - number(prev, f)?).max(0.0) / secs))
+        }
+        Expr::Bin(lhs, op, rhs) => {
+            let (Value::Num(a), Value::Num(b)) = (eval(lhs, target)?, eval(rhs, target)?) else {
+                return None;
+            };
+            let v: f64 = match op {
+                '+' => a + b,
+                '-' => a - b,
+                '*' => a * b,
+                _ if b == 0.0 => return None,
+                _ => a / b,
+            };
+            Some(Value::Num(v))
+        }
+    }
+}
+
+impl Condition {
+    /// Whether the condition holds for a node, and the left-hand value.
+    ///
+    /// `None` when it does not apply, e.g. the field is missing or the node
+    /// is unreachable.
+    pub(crate) fn check(&self, target: &Target) -> Option<(bool, String)> {
+        let lhs: Value = eval(&self.lhs, target)?;
+        let rhs: Value = match (&self.rhs, eval(&self.rhs, target)) {
+            (_, Some(v)) => v,
+            (Expr::Field(word), None) => Value::Text(word.clone()),
+            (_, None) => return None,
+        };
+        let holds: bool = match (&lhs, &rhs, self.cmp) {
+            (Value::Num(a), Value::Num(b), cmp) => match cmp {
+                Cmp::Gt => a > b,
+                Cmp::Ge => a >= b,
+                Cmp::Lt => a < b,
+                Cmp::Le => a <= b,
+                Cmp::Eq => a == b,
+                Cmp::Ne => a != b,
+            },
+            (_, _, Cmp::Eq) => lhs.to_string() == rhs.to_string(),
+            (_, _, Cmp::Ne) => lhs.to_string() != rhs.to_string(),
+            _ => return None,
+        };
+        Some((holds, lhs.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::NodeProbe, RedisInstance};
+
+    fn report(fields: &[(&str, &str)]) -> NodeReport {
+        let info: InfoMap = fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let instance = RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() };
+        NodeReport::new(&instance, "master", None, Ok(NodeProbe { info, ..Default::default() }))
+    }
+
+    fn check(src: &str, report: &NodeReport) -> Option<(bool, String)> {
+        let target = Target { report, prev: None, ts: 0 };
+        parse(src).unwrap().check(&target)
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let node: NodeReport = report(&[]);
+        assert_eq!(check("1 + 2 * 3 == 7", &node), Some((true, "7".to_string())));
+        assert_eq!(check("(1 + 2) * 3 == 9", &node), Some((true, "9".to_string())));
+        assert_eq!(check("10 - 4 - 3 == 3", &node), Some((true, "3".to_string())));
+        assert_eq!(check("8 / 4 / 2 == 1", &node), Some((true, "1".to_string())));
+    }
+
+    #[test]
+    fn fields_are_read_from_info() {
+        let node: NodeReport = report(&[("used_memory", "60"), ("maxmemory", "100")]);
+        assert_eq!(check("used_memory / maxmemory > 0.5", &node), Some((true, "0.6".to_string())));
+        assert_eq!(check("used_memory / maxmemory > 0.9", &node), Some((false, "0.6".to_string())));
+        assert_eq!(check("status == up", &node), Some((true, "up".to_string())));
+        assert_eq!(check("role == master", &node), Some((true, "master".to_string())));
+        assert_eq!(check("evicted_keys > 0", &node), None);
+    }
+
+    #[test]
+    fn bare_words_compare_as_text() {
+        let down: NodeReport = report(&[("master_link_status", "down")]);
+        let up: NodeReport = report(&[("master_link_status", "up")]);
+        assert_eq!(check("master_link_status != up", &down), Some((true, "down".to_string())));
+        assert_eq!(check("master_link_status != up", &up), Some((false, "up".to_string())));
+        assert_eq!(check("master_link_status == 'up'", &up), Some((true, "up".to_string())));
+        // Text only supports equality.
+        assert_eq!(check("master_link_status > up", &up), None);
+    }
+
+    #[test]
+    fn division_by_zero_does_not_apply() {
+        let node: NodeReport = report(&[("used_memory", "60"), ("maxmemory", "0")]);
+        assert_eq!(check("used_memory / maxmemory > 0.9", &node), None);
+    }
+
+    #[test]
+    fn rate_is_per_second_since_the_previous_poll() {
+        let node: NodeReport = report(&[("total_commands_processed", "300")]);
+        let prev: InfoMap = [("total_commands_processed".to_string(), "100".to_string())].into();
+        let condition: Condition = parse("rate(total_commands_processed) > 99").unwrap();
+        let target = Target { report: &node, prev: Some((&prev, 1000)), ts: 3000 };
+        assert_eq!(condition.check(&target), Some((true, "100".to_string())));
+        // No previous poll, or a counter that went backwards after a restart
+        assert_eq!(condition.check(&Target { report: &node, prev: None, ts: 3000 }), None);
+        let restarted: InfoMap = [("total_commands_processed".to_string(), "900".to_string())].into();
+        let target = Target { report: &node, prev: Some((&restarted, 1000)), ts: 3000 };
+        assert_eq!(condition.check(&target), Some((false, "0".to_string())));
+    }
+
+    #[test]
+    fn for_takes_a_duration_with_a_unit() {
+        assert_eq!(parse("status != up").unwrap().hold_ms, 0);
+        assert_eq!(parse("status != up for 250ms").unwrap().hold_ms, 250);
+        assert_eq!(parse("status != up for 90").unwrap().hold_ms, 90_000);
+        assert_eq!(parse("status != up for 90s").unwrap().hold_ms, 90_000);
+        assert_eq!(parse("status != up for 5m").unwrap().hold_ms, 300_000);
+        assert_eq!(parse("status != up for 2h").unwrap().hold_ms, 7_200_000);
+        assert!(parse("status != up for 3d").is_err());
+        assert!(parse("status != up for soon").is_err());
+        assert!(parse("status != up for 18446744073709551615h").is_err());
+        assert!(parse("status != up for 99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn invalid_conditions_are_rejected() {
+        for src in [
+            "",
+            "used_memory",
+            "used_memory >",
+            "used_memory 5",
+            "used_memory > 5 6",
+            "(used_memory > 5",
+            "used_memory > 5)",
+            "rate(5) > 1",
+            "rate(used_memory > 1",
+            "used_memory $ 5",
+            "master_link_status == 'up",
+        ] {
+            assert!(parse(src).is_err(), "{:?} should not parse", src);
+        }
+    }
+}
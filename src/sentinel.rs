@@ -0,0 +1,318 @@
+use redis::aio::MultiplexedConnection;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    addr,
+    collector::{collect_master, parse_redis_info, query, task_failed, NodeReport, NodeStatus, Snapshot},
+    error::{CollectError, ErrorReport},
+    pool::ConnectionPool,
+    RedisCluster, RedisInstance,
+};
+
+/// One monitored master as seen by one Sentinel.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SentinelMasterView {
+    pub(crate) name: String,
+    pub(crate) ip: String,
+    pub(crate) port: String,
+    /// e.g. `master`, `master,s_down`, `master,s_down,o_down`
+    pub(crate) flags: String,
+    pub(crate) quorum: String,
+    pub(crate) num_slaves: String,
+    pub(crate) num_other_sentinels: String,
+    /// Whether `SENTINEL CKQUORUM` says a failover could be authorized.
+    pub(crate) quorum_ok: bool,
+    pub(crate) quorum_message: String,
+}
+
+/// Health of one Sentinel and its view of every master it monitors.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SentinelReport {
+    pub(crate) ip: String,
+    pub(crate) port: String,
+    pub(crate) status: NodeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<ErrorReport>,
+    /// False for Sentinels only known through their peers.
+    pub(crate) configured: bool,
+    pub(crate) masters: Vec<SentinelMasterView>,
+}
+
+struct SentinelProbe {
+    /// `run_id` from the Sentinel's INFO, to recognise it under another address.
+    run_id: Option<String>,
+    masters: Vec<SentinelMasterView>,
+    peers: Vec<Peer>,
+}
+
+// Another Sentinel as announced by `SENTINEL SENTINELS`.
+struct Peer {
+    instance: RedisInstance,
+    run_id: Option<String>,
+}
+
+// Sentinel replies describe each entity as a flat list of alternating field names and values.
+fn fields(flat: Vec<String>) -> HashMap<String, String> {
+    let mut map: HashMap<String, String> = HashMap::new();
+    let mut items = flat.into_iter();
+    while let (Some(k), Some(v)) = (items.next(), items.next()) {
+        map.insert(k, v);
+    }
+    map
+}
+
+fn instance_of(fields: &HashMap<String, String>) -> Option<RedisInstance> {
+    addr::instance(fields.get("ip")?, fields.get("port")?)
+}
+
+// One entry of `SENTINEL MASTERS`, before its quorum was checked.
+fn parse_master(reply: Vec<String>) -> Result<SentinelMasterView, CollectError> {
+    let master: HashMap<String, String> = fields(reply);
+    let get = |k: &str| master.get(k).cloned().unwrap_or_default();
+    let name: String = get("name");
+    if name.is_empty() {
+        return Err(CollectError::Parse("SENTINEL MASTERS entry without a name".to_string()));
+    }
+    Ok(SentinelMasterView {
+        ip: get("ip"),
+        port: get("port"),
+        flags: get("flags"),
+        quorum: get("quorum"),
+        num_slaves: get("num-slaves"),
+        num_other_sentinels: get("num-other-sentinels"),
+        quorum_ok: false,
+        quorum_message: String::new(),
+        name,
+    })
+}
+
+// Addresses of the entries of `SENTINEL REPLICAS` or `SENTINEL SENTINELS`.
+fn parse_instances(replies: Vec<Vec<String>>) -> Vec<(RedisInstance, HashMap<String, String>)> {
+    replies.into_iter()
+        .map(fields)
+        .filter_map(|fields| Some((instance_of(&fields)?, fields)))
+        .collect()
+}
+
+async fn probe_sentinel(pool: &ConnectionPool, sentinel: &RedisInstance) -> Result<SentinelProbe, CollectError> {
+    let mut con: MultiplexedConnection = pool.get(sentinel).await?;
+    let info: String = query(pool, sentinel, &mut con, redis::cmd("INFO").arg("server")).await?;
+    let run_id: Option<String> = parse_redis_info(&info).remove("run_id");
+    let replies: Vec<Vec<String>> = query(pool, sentinel, &mut con, redis::cmd("SENTINEL").arg("MASTERS")).await?;
+
+    let mut masters: Vec<SentinelMasterView> = Vec::new();
+    let mut peers: Vec<Peer> = Vec::new();
+    for reply in replies {
+        let mut master: SentinelMasterView = parse_master(reply)?;
+        (master.quorum_ok, master.quorum_message) = match query::<String>(
+            pool, sentinel, &mut con, redis::cmd("SENTINEL").arg("CKQUORUM").arg(&master.name),
+        ).await {
+            Ok(message) => (true, message),
+            Err(e @ CollectError::Redis(_)) => (false, e.to_string()),
+            Err(e) => return Err(e),
+        };
+        let others: Vec<Vec<String>> = query(
+            pool, sentinel, &mut con, redis::cmd("SENTINEL").arg("SENTINELS").arg(&master.name),
+        ).await?;
+        peers.extend(parse_instances(others).into_iter()
+            .map(|(instance, mut fields)| Peer { instance, run_id: fields.remove("runid") }));
+        masters.push(master);
+    }
+    masters.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(SentinelProbe { run_id, masters, peers })
+}
+
+async fn get_sentinel_replicas(
+    pool: &ConnectionPool,
+    sentinel: &RedisInstance,
+    name: &str,
+) -> Result<Vec<RedisInstance>, CollectError> {
+    let mut con: MultiplexedConnection = pool.get(sentinel).await?;
+    let replies: Vec<Vec<String>> = query(
+        pool, sentinel, &mut con, redis::cmd("SENTINEL").arg("REPLICAS").arg(name),
+    ).await?;
+    Ok(parse_instances(replies).into_iter().map(|(instance, _)| instance).collect())
+}
+
+fn probe_all(
+    pool: &Arc<ConnectionPool>,
+    sentinels: Vec<RedisInstance>,
+) -> Vec<(RedisInstance, JoinHandle<Result<SentinelProbe, CollectError>>)> {
+    sentinels.into_iter()
+        .map(|sentinel| {
+            let pool = pool.clone();
+            let target: RedisInstance = sentinel.clone();
+            (sentinel, tokio::spawn(async move { probe_sentinel(&pool, &target).await }))
+        })
+        .collect()
+}
+
+/// The master address most Sentinels agree on, per master name, with a
+/// Sentinel that reports it.
+///
+/// Ties go to the address reported by the earliest Sentinel.
+fn elect(views: &[(RedisInstance, Vec<SentinelMasterView>)]) -> Vec<(String, RedisInstance, RedisInstance)> {
+    let mut votes: BTreeMap<&str, Vec<(&str, &str, usize, &RedisInstance)>> = BTreeMap::new();
+    for (sentinel, masters) in views {
+        for m in masters {
+            let candidates = votes.entry(&m.name).or_default();
+            match candidates.iter_mut().find(|(ip, port, _, _)| *ip == m.ip && *port == m.port) {
+                Some(candidate) => candidate.2 += 1,
+                None => candidates.push((&m.ip, &m.port, 1, sentinel)),
+            }
+        }
+    }
+    votes.into_iter()
+        .filter_map(|(name, candidates)| {
+            let mut best = &candidates[0];
+            for candidate in &candidates {
+                if candidate.2 > best.2 {
+                    best = candidate;
+                }
+            }
+            Some((name.to_string(), addr::instance(best.0, best.1)?, best.3.clone()))
+        })
+        .collect()
+}
+
+/// Collects a Sentinel-managed deployment.
+///
+/// Every configured Sentinel, plus any peer they announce, is asked for the
+/// masters it monitors. The address most Sentinels agree on is polled as the
+/// master, so a failover is followed on the next tick, and replicas come from
+/// `SENTINEL REPLICAS` rather than from the master's INFO. A Sentinel reached
+/// under two addresses, e.g. configured by hostname and announced by IP, is
+/// recognised by its run ID and only counted once.
+pub(crate) async fn collect_sentinel(
+    pool: &Arc<ConnectionPool>,
+    sentinel_pool: &Arc<ConnectionPool>,
+    cluster: &RedisCluster,
+) -> Snapshot {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    let mut reports: Vec<SentinelReport> = Vec::new();
+    let mut views: Vec<(RedisInstance, Vec<SentinelMasterView>)> = Vec::new();
+
+    let mut pending: Vec<RedisInstance> = cluster.instances.clone();
+    let mut configured: bool = true;
+    // Configured Sentinels first, then the peers they announced.
+    while !pending.is_empty() {
+        pending.retain(|s| seen.insert(format!("{}:{}", s.ip, s.port)));
+        let mut peers: Vec<Peer> = Vec::new();
+        for (sentinel, handle) in probe_all(sentinel_pool, std::mem::take(&mut pending)) {
+            let probe = handle.await.unwrap_or_else(|e| Err(task_failed(e)));
+            let (status, error, masters) = match probe {
+                Ok(probe) => {
+                    if probe.run_id.as_ref().is_some_and(|id| !seen_ids.insert(id.clone())) {
+                        continue;
+                    }
+                    peers.extend(probe.peers);
+                    views.push((sentinel.clone(), probe.masters.clone()));
+                    (NodeStatus::Up, None, probe.masters)
+                }
+                Err(e @ CollectError::Timeout(_)) => (NodeStatus::Timeout, Some(ErrorReport::from(&e)), Vec::new()),
+                Err(e) => (NodeStatus::Down, Some(ErrorReport::from(&e)), Vec::new()),
+            };
+            reports.push(SentinelReport {
+                ip: sentinel.ip.clone(),
+                port: sentinel.port.clone(),
+                status,
+                error,
+                configured,
+                masters,
+            });
+        }
+        pending = peers.into_iter()
+            .filter(|peer| peer.run_id.as_ref().is_none_or(|id| !seen_ids.contains(id)))
+            .map(|peer| peer.instance)
+            .collect();
+        configured = false;
+    }
+
+    let mut handles: Vec<(RedisInstance, JoinHandle<Vec<NodeReport>>)> = Vec::new();
+    for (name, master, agreeing) in elect(&views) {
+        // Ask a Sentinel that agrees on the master for its replicas.
+        let replicas: Vec<RedisInstance> = get_sentinel_replicas(sentinel_pool, &agreeing, &name).await.unwrap_or_default();
+        handles.push((master.clone(), tokio::spawn(collect_master(pool.clone(), master, Some(replicas)))));
+    }
+
+    let mut nodes: Vec<NodeReport> = Vec::new();
+    for (master, handle) in handles {
+        match handle.await {
+            Ok(reports) => nodes.extend(reports),
+            Err(e) => nodes.push(NodeReport::new(&master, "master", None, Err(task_failed(e)))),
+        }
+    }
+    Snapshot { nodes, sentinels: reports, ..Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(pairs: &[(&str, &str)]) -> Vec<String> {
+        pairs.iter().flat_map(|(k, v)| [k.to_string(), v.to_string()]).collect()
+    }
+
+    fn sentinel(ip: &str) -> RedisInstance {
+        RedisInstance { ip: ip.to_string(), port: "26379".to_string(), ..Default::default() }
+    }
+
+    fn view(name: &str, ip: &str) -> SentinelMasterView {
+        parse_master(flat(&[("name", name), ("ip", ip), ("port", "6379")])).unwrap()
+    }
+
+    #[test]
+    fn masters_are_parsed_from_field_value_pairs() {
+        let master: SentinelMasterView = parse_master(flat(&[
+            ("name", "mymaster"), ("ip", "10.0.0.1"), ("port", "6379"), ("runid", "abc"),
+            ("flags", "master,s_down"), ("num-slaves", "2"), ("num-other-sentinels", "2"), ("quorum", "2"),
+        ])).unwrap();
+        assert_eq!((master.ip.as_str(), master.port.as_str()), ("10.0.0.1", "6379"));
+        assert_eq!(master.flags, "master,s_down");
+        assert_eq!((master.num_slaves.as_str(), master.num_other_sentinels.as_str()), ("2", "2"));
+        assert_eq!(master.quorum, "2");
+        assert!(parse_master(flat(&[("ip", "10.0.0.1"), ("port", "6379")])).is_err());
+    }
+
+    #[test]
+    fn replicas_and_peers_without_an_address_are_skipped() {
+        let replies: Vec<Vec<String>> = vec![
+            flat(&[("name", "10.0.0.2:6379"), ("ip", "10.0.0.2"), ("port", "6379"), ("flags", "slave")]),
+            flat(&[("name", "broken"), ("ip", "10.0.0.3")]),
+            flat(&[("name", "10.0.0.4:6380"), ("ip", "10.0.0.4"), ("port", "6380"), ("runid", "def")]),
+        ];
+        let parsed = parse_instances(replies);
+        let addrs: Vec<String> = parsed.iter().map(|(i, _)| format!("{}:{}", i.ip, i.port)).collect();
+        assert_eq!(addrs, ["10.0.0.2:6379", "10.0.0.4:6380"]);
+        assert_eq!(parsed[1].1.get("runid").map(String::as_str), Some("def"));
+    }
+
+    #[test]
+    fn the_majority_address_wins_the_vote() {
+        let views = vec![
+            (sentinel("10.0.1.1"), vec![view("mymaster", "10.0.0.1"), view("other", "10.0.0.9")]),
+            (sentinel("10.0.1.2"), vec![view("mymaster", "10.0.0.2")]),
+            (sentinel("10.0.1.3"), vec![view("mymaster", "10.0.0.2")]),
+        ];
+        let elected = elect(&views);
+        let summary: Vec<(&str, &str, &str)> = elected.iter()
+            .map(|(name, master, agreeing)| (name.as_str(), master.ip.as_str(), agreeing.ip.as_str()))
+            .collect();
+        assert_eq!(summary, [("mymaster", "10.0.0.2", "10.0.1.2"), ("other", "10.0.0.9", "10.0.1.1")]);
+    }
+
+    #[test]
+    fn a_tie_goes_to_the_earliest_sentinel() {
+        let views = vec![
+            (sentinel("10.0.1.1"), vec![view("mymaster", "10.0.0.1")]),
+            (sentinel("10.0.1.2"), vec![view("mymaster", "10.0.0.2")]),
+        ];
+        assert_eq!(elect(&views)[0].1.ip, "10.0.0.1");
+    }
+}
@@ -0,0 +1,316 @@
+use redis::{aio::MultiplexedConnection, Value};
+use serde::Serialize;
+
+use crate::{
+    collector::{diagnostic, NodeReport},
+    history::info_num,
+    nodelog::{Logged, NodeLog},
+    pool::ConnectionPool,
+    reply::{int, text},
+    RedisInstance, SlowlogRedaction,
+};
+
+// Entries read at most per node and tick; the default `slowlog-max-len`.
+const SLOWLOG_FETCH: u64 = 128;
+
+/// One entry of `SLOWLOG GET`, with its arguments as logged by Redis.
+#[derive(Debug, Clone)]
+pub(crate) struct SlowEntry {
+    id: u64,
+    ts: u64,
+    duration_us: u64,
+    args: Vec<String>,
+    client_addr: String,
+    client_name: String,
+}
+
+/// A slow query of one node, with its arguments redacted as configured.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SlowQuery {
+    /// `ip:port` of the node.
+    pub(crate) node: String,
+    /// Entry ID on that node.
+    pub(crate) id: u64,
+    /// Unix milliseconds the command was run at.
+    pub(crate) ts: u64,
+    pub(crate) duration_us: u64,
+    pub(crate) command: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) client_addr: String,
+    pub(crate) client_name: String,
+}
+
+/// Parses the reply of `SLOWLOG GET`, newest first.
+///
+/// Client address and name are only logged since Redis 4.0.
+pub(crate) fn parse_slowlog(reply: &Value) -> Vec<SlowEntry> {
+    let Value::Bulk(entries) = reply else {
+        return Vec::new();
+    };
+    entries.iter()
+        .filter_map(|entry| {
+            let Value::Bulk(fields) = entry else {
+                return None;
+            };
+            let args: Vec<String> = match fields.get(3)? {
+                Value::Bulk(args) => args.iter().map(|arg| text(arg).unwrap_or_default()).collect(),
+                _ => Vec::new(),
+            };
+            Some(SlowEntry {
+                id: int(fields.first()?)?,
+                ts: int(fields.get(1)?)? * 1000,
+                duration_us: int(fields.get(2)?)?,
+                args,
+                client_addr: fields.get(4).and_then(text).unwrap_or_default(),
+                client_name: fields.get(5).and_then(text).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+async fn slowlog_get(pool: &ConnectionPool, instance: &RedisInstance, con: &mut MultiplexedConnection, count: u64) -> Vec<SlowEntry> {
+    let reply: Option<Value> = diagnostic(pool, instance, con, redis::cmd("SLOWLOG").arg("GET").arg(count)).await;
+    reply.as_ref().map(parse_slowlog).unwrap_or_default()
+}
+
+// How many entries to read when the newest one is `newest`, `None` when
+// reading it alone was enough.
+fn fetch_count(last_id: u64, newest: Option<u64>) -> Option<u64> {
+    match newest {
+        Some(newest) if newest > last_id + 1 => Some((newest - last_id).min(SLOWLOG_FETCH)),
+        // Nothing new, a single new entry, or a slowlog emptied by `SLOWLOG RESET`,
+        // which keeps counting IDs from where it was.
+        _ => None,
+    }
+}
+
+/// Reads the entries a node logged after `last_id`, the newest one logged so far.
+///
+/// Only the newest entry is asked for until its ID moves, so a quiet
+/// slowlog costs one short reply per tick. The first read of a node, or
+/// one after it restarted, takes up to `SLOWLOG_FETCH` entries.
+pub(crate) async fn read_slowlog(
+    pool: &ConnectionPool,
+    instance: &RedisInstance,
+    con: &mut MultiplexedConnection,
+    last_id: Option<u64>,
+) -> Vec<SlowEntry> {
+    let count: u64 = match last_id {
+        None => SLOWLOG_FETCH,
+        Some(last) => {
+            let head: Vec<SlowEntry> = slowlog_get(pool, instance, con, 1).await;
+            match fetch_count(last, head.first().map(|e| e.id)) {
+                Some(count) => count,
+                None => return head,
+            }
+        }
+    };
+    slowlog_get(pool, instance, con, count).await
+}
+
+fn redact(args: &[String], redaction: SlowlogRedaction) -> Vec<String> {
+    let kept: usize = match redaction {
+        SlowlogRedaction::None => args.len(),
+        SlowlogRedaction::Values => 1,
+        SlowlogRedaction::All => 0,
+    };
+    args.iter().enumerate()
+        .map(|(i, arg)| if i < kept { arg.clone() } else { "?".to_string() })
+        .collect()
+}
+
+impl Logged for SlowQuery {
+    fn ts(&self) -> u64 {
+        self.ts
+    }
+}
+
+// How far the slowlog of a node was logged.
+#[derive(Default)]
+struct Cursor {
+    run_id: String,
+    uptime: u64,
+    // ID of the newest entry logged
+    last_id: Option<u64>,
+}
+
+impl Cursor {
+    // Entry IDs start over when a node restarts.
+    fn restarted(&self, report: &NodeReport) -> bool {
+        report.info.get("run_id").map(String::as_str).unwrap_or_default() != self.run_id
+            || info_num::<u64>(&report.info, "uptime_in_seconds") < self.uptime
+    }
+}
+
+/// Bounded log of the slow queries of one cluster.
+pub(crate) struct SlowLog {
+    redaction: SlowlogRedaction,
+    log: NodeLog<Cursor, SlowQuery>,
+}
+
+impl SlowLog {
+    pub(crate) fn new(redaction: SlowlogRedaction) -> Self {
+        SlowLog { redaction, log: NodeLog::default() }
+    }
+
+    /// Logs the entries newer than the last one seen on every reachable node.
+    ///
+    /// A node whose run ID changed or whose uptime went back has restarted
+    /// and numbers its entries from zero again, so all of them are new.
+    pub(crate) fn observe(&self, reports: &[NodeReport]) -> Vec<SlowQuery> {
+        self.log.observe(reports, |report, cursor| {
+            let last: Option<u64> = if cursor.restarted(report) { None } else { cursor.last_id };
+            let queries: Vec<SlowQuery> = report.slowlog.iter().rev()
+                .filter(|e| last.is_none_or(|last| e.id > last))
+                .map(|entry| {
+                    let (command, args) = entry.args.split_first().map(|(c, a)| (c.clone(), a)).unwrap_or_default();
+                    SlowQuery {
+                        node: report.addr(),
+                        id: entry.id,
+                        ts: entry.ts,
+                        duration_us: entry.duration_us,
+                        command,
+                        args: redact(args, self.redaction),
+                        client_addr: entry.client_addr.clone(),
+                        client_name: entry.client_name.clone(),
+                    }
+                })
+                .collect();
+            *cursor = Cursor {
+                run_id: report.info.get("run_id").cloned().unwrap_or_default(),
+                uptime: info_num(&report.info, "uptime_in_seconds"),
+                last_id: report.slowlog.iter().map(|e| e.id).max().max(last),
+            };
+            queries
+        })
+    }
+
+    /// ID of the newest entry logged for the node of `report`, `None` until
+    /// one was or when the node has restarted since.
+    pub(crate) fn last_id(&self, report: &NodeReport) -> Option<u64> {
+        self.log.state(&report.addr(), |cursor| cursor.last_id.filter(|_| !cursor.restarted(report))).flatten()
+    }
+
+    /// Logged slow queries newer than `since` (unix milliseconds).
+    pub(crate) fn since(&self, since: u64) -> Vec<SlowQuery> {
+        self.log.since(since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collector::{parse_redis_info, NodeProbe}, RedisInstance};
+
+    fn data(s: &str) -> Value {
+        Value::Data(s.as_bytes().to_vec())
+    }
+
+    fn entry(id: u64) -> SlowEntry {
+        SlowEntry {
+            id,
+            ts: 1000 * id,
+            duration_us: 20_000,
+            args: vec!["SET".to_string(), "user:1".to_string(), "secret".to_string()],
+            client_addr: "10.0.1.5:50412".to_string(),
+            client_name: "worker".to_string(),
+        }
+    }
+
+    // A poll of the node returning the entries `ids`, newest first as `SLOWLOG GET` does.
+    fn up(run_id: &str, uptime: u64, ids: &[u64]) -> NodeReport {
+        let instance = RedisInstance { ip: "10.0.0.1".to_string(), port: "6379".to_string(), ..Default::default() };
+        let info = parse_redis_info(&format!("run_id:{}\r\nuptime_in_seconds:{}\r\n", run_id, uptime));
+        let mut report = NodeReport::new(&instance, "master", None, Ok(NodeProbe { info, ..Default::default() }));
+        report.slowlog = ids.iter().rev().map(|id| entry(*id)).collect();
+        report
+    }
+
+    fn ids(queries: &[SlowQuery]) -> Vec<u64> {
+        queries.iter().map(|q| q.id).collect()
+    }
+
+    #[test]
+    fn entries_are_parsed_with_and_without_the_client() {
+        let reply = Value::Bulk(vec![
+            Value::Bulk(vec![
+                Value::Int(14), Value::Int(1700000000), Value::Int(20_000),
+                Value::Bulk(vec![data("HGETALL"), data("user:1")]),
+                data("10.0.1.5:50412"), data("worker"),
+            ]),
+            // Before Redis 4.0
+            Value::Bulk(vec![Value::Int(13), Value::Int(1699999999), Value::Int(15_000), Value::Bulk(vec![data("KEYS"), data("*")])]),
+            Value::Bulk(vec![Value::Int(12)]),
+        ]);
+        let entries: Vec<SlowEntry> = parse_slowlog(&reply);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].id, entries[0].ts, entries[0].duration_us), (14, 1700000000000, 20_000));
+        assert_eq!(entries[0].args, ["HGETALL", "user:1"]);
+        assert_eq!((entries[0].client_addr.as_str(), entries[0].client_name.as_str()), ("10.0.1.5:50412", "worker"));
+        assert_eq!((entries[1].client_addr.as_str(), entries[1].client_name.as_str()), ("", ""));
+        assert!(parse_slowlog(&Value::Nil).is_empty());
+    }
+
+    #[test]
+    fn arguments_are_redacted_as_configured() {
+        let args: Vec<String> = vec!["user:1".to_string(), "secret".to_string()];
+        assert_eq!(redact(&args, SlowlogRedaction::None), ["user:1", "secret"]);
+        assert_eq!(redact(&args, SlowlogRedaction::Values), ["user:1", "?"]);
+        assert_eq!(redact(&args, SlowlogRedaction::All), ["?", "?"]);
+
+        let log = SlowLog::new(SlowlogRedaction::Values);
+        let queries: Vec<SlowQuery> = log.observe(&[up("aaa", 100, &[1])]);
+        assert_eq!((queries[0].command.as_str(), queries[0].args.clone()), ("SET", vec!["user:1".to_string(), "?".to_string()]));
+    }
+
+    #[test]
+    fn a_quiet_node_is_read_one_entry_at_a_time() {
+        assert_eq!(fetch_count(10, Some(10)), None);
+        assert_eq!(fetch_count(10, Some(11)), None);
+        assert_eq!(fetch_count(10, Some(15)), Some(5));
+
+        let log = SlowLog::new(SlowlogRedaction::None);
+        assert_eq!(ids(&log.observe(&[up("aaa", 100, &[8, 9, 10])])), [8, 9, 10]);
+        assert!(log.observe(&[up("aaa", 101, &[10])]).is_empty());
+        assert_eq!(ids(&log.observe(&[up("aaa", 102, &[11])])), [11]);
+        assert_eq!(log.last_id(&up("aaa", 103, &[])), Some(11));
+    }
+
+    #[test]
+    fn a_gap_above_the_fetch_size_reads_the_newest_entries() {
+        assert_eq!(fetch_count(10, Some(10 + SLOWLOG_FETCH)), Some(SLOWLOG_FETCH));
+        assert_eq!(fetch_count(10, Some(1000)), Some(SLOWLOG_FETCH));
+
+        let log = SlowLog::new(SlowlogRedaction::None);
+        log.observe(&[up("aaa", 100, &[10])]);
+        let newest: Vec<u64> = (1000 - SLOWLOG_FETCH + 1..=1000).collect();
+        assert_eq!(ids(&log.observe(&[up("aaa", 101, &newest)])), newest);
+    }
+
+    #[test]
+    fn a_restart_logs_the_renumbered_entries() {
+        let log = SlowLog::new(SlowlogRedaction::None);
+        log.observe(&[up("aaa", 100, &[40, 41])]);
+        // Fewer entries than before the restart, but also IDs above the last one
+        let restarted: NodeReport = up("bbb", 5, &[0, 1, 42]);
+        assert_eq!(log.last_id(&restarted), None);
+        assert_eq!(ids(&log.observe(&[restarted])), [0, 1, 42]);
+        assert!(log.observe(&[up("bbb", 6, &[42])]).is_empty());
+
+        // Same run ID, but the uptime went back
+        assert_eq!(log.last_id(&up("bbb", 1, &[])), None);
+        assert_eq!(ids(&log.observe(&[up("bbb", 1, &[3])])), [3]);
+    }
+
+    #[test]
+    fn slowlog_reset_keeps_counting_ids() {
+        assert_eq!(fetch_count(41, None), None);
+
+        let log = SlowLog::new(SlowlogRedaction::None);
+        log.observe(&[up("aaa", 100, &[40, 41])]);
+        assert!(log.observe(&[up("aaa", 101, &[])]).is_empty());
+        assert_eq!(log.last_id(&up("aaa", 102, &[])), Some(41));
+        assert_eq!(ids(&log.observe(&[up("aaa", 102, &[42])])), [42]);
+        assert_eq!(ids(&log.since(40_000)), [41, 42]);
+    }
+}